@@ -0,0 +1,94 @@
+use std::{fmt, fs, io, path::Path};
+
+use super::model::Model;
+
+/// Writing a value with bit 0 set to this register unmaps the boot ROM until the next reset.
+pub const BOOT_ROM_DISABLE_ADDRESS: u16 = 0xFF50;
+
+/// The cartridge header, which stays visible while a CGB boot ROM is mapped.
+const CARTRIDGE_HEADER_START: u16 = 0x100;
+const CARTRIDGE_HEADER_END: u16 = 0x200;
+
+#[derive(Debug)]
+pub enum BootRomError {
+    Io(io::Error),
+    /// The image doesn't match the size of the model's boot ROM.
+    WrongSize {
+        model: Model,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for BootRomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Failed to read the boot ROM: {error}"),
+            Self::WrongSize {
+                model,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "The {model} boot ROM must be {expected} bytes! (found {actual} bytes)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BootRomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::WrongSize { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for BootRomError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// A boot ROM image, overlaid on the start of the cartridge ROM until the program disables it.
+#[derive(Debug, Clone)]
+pub struct BootRom {
+    data: Vec<u8>,
+}
+
+impl BootRom {
+    /// # Errors
+    ///
+    /// Returns an error if `data` isn't the size of `model`'s boot ROM.
+    pub fn new(model: Model, data: Vec<u8>) -> Result<Self, BootRomError> {
+        let expected = model.boot_rom_size();
+        if data.len() != expected {
+            return Err(BootRomError::WrongSize {
+                model,
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        Ok(Self { data })
+    }
+
+    /// Reads a boot ROM image from `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't the size of `model`'s boot ROM.
+    pub fn from_path(model: Model, path: impl AsRef<Path>) -> Result<Self, BootRomError> {
+        Self::new(model, fs::read(path)?)
+    }
+
+    /// Returns the byte the boot ROM maps at `address`, or `None` if the cartridge shows through.
+    pub fn read(&self, address: u16) -> Option<u8> {
+        if (CARTRIDGE_HEADER_START..CARTRIDGE_HEADER_END).contains(&address) {
+            return None;
+        }
+
+        self.data.get(usize::from(address)).copied()
+    }
+}
@@ -0,0 +1,344 @@
+use std::fmt;
+
+use super::CartridgeError;
+
+pub(crate) const TITLE_START: usize = 0x134;
+const TITLE_END: usize = 0x143;
+pub(crate) const CGB_FLAG_ADDRESS: usize = 0x143;
+pub(crate) const NEW_LICENSEE_CODE_ADDRESS: usize = 0x144;
+const SGB_FLAG_ADDRESS: usize = 0x146;
+const CARTRIDGE_TYPE_ADDRESS: usize = 0x147;
+const ROM_SIZE_ADDRESS: usize = 0x148;
+const RAM_SIZE_ADDRESS: usize = 0x149;
+pub(crate) const OLD_LICENSEE_CODE_ADDRESS: usize = 0x14B;
+const VERSION_ADDRESS: usize = 0x14C;
+pub(crate) const HEADER_CHECKSUM_ADDRESS: usize = 0x14D;
+const GLOBAL_CHECKSUM_ADDRESS: usize = 0x14E;
+
+/// The first address past the cartridge header.
+pub const HEADER_END: usize = 0x150;
+
+/// An old licensee code of `0x33` means the new licensee code is used instead.
+const USE_NEW_LICENSEE_CODE: u8 = 0x33;
+
+/// How a cartridge makes use of the Game Boy Color's features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgbSupport {
+    /// The cartridge predates the Game Boy Color.
+    None,
+    /// The cartridge uses CGB features but still runs on older models.
+    Enhanced,
+    /// The cartridge only runs on a Game Boy Color.
+    Required,
+}
+
+/// The chip that maps the cartridge's ROM and RAM into the address space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controller {
+    RomOnly,
+    Mbc1,
+    Mbc2,
+    Mbc3,
+    Mbc5,
+    Mbc6,
+    Mbc7,
+    Mmm01,
+    PocketCamera,
+    Tama5,
+    HuC1,
+    HuC3,
+}
+
+/// The hardware on a cartridge, as declared by its cartridge type byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CartridgeType {
+    pub code: u8,
+    pub controller: Controller,
+    pub has_ram: bool,
+    pub has_battery: bool,
+    pub has_timer: bool,
+    pub has_rumble: bool,
+}
+
+impl CartridgeType {
+    /// Decodes the cartridge type byte at `0x147`, returning `None` for unknown codes.
+    pub const fn from_code(code: u8) -> Option<Self> {
+        let (controller, has_ram, has_battery, has_timer, has_rumble) = match code {
+            0x00 => (Controller::RomOnly, false, false, false, false),
+            0x01 => (Controller::Mbc1, false, false, false, false),
+            0x02 => (Controller::Mbc1, true, false, false, false),
+            0x03 => (Controller::Mbc1, true, true, false, false),
+            0x05 => (Controller::Mbc2, false, false, false, false),
+            0x06 => (Controller::Mbc2, false, true, false, false),
+            0x08 => (Controller::RomOnly, true, false, false, false),
+            0x09 => (Controller::RomOnly, true, true, false, false),
+            0x0B => (Controller::Mmm01, false, false, false, false),
+            0x0C => (Controller::Mmm01, true, false, false, false),
+            0x0D => (Controller::Mmm01, true, true, false, false),
+            0x0F => (Controller::Mbc3, false, true, true, false),
+            0x10 => (Controller::Mbc3, true, true, true, false),
+            0x11 => (Controller::Mbc3, false, false, false, false),
+            0x12 => (Controller::Mbc3, true, false, false, false),
+            0x13 => (Controller::Mbc3, true, true, false, false),
+            0x19 => (Controller::Mbc5, false, false, false, false),
+            0x1A => (Controller::Mbc5, true, false, false, false),
+            0x1B => (Controller::Mbc5, true, true, false, false),
+            0x1C => (Controller::Mbc5, false, false, false, true),
+            0x1D => (Controller::Mbc5, true, false, false, true),
+            0x1E => (Controller::Mbc5, true, true, false, true),
+            0x20 => (Controller::Mbc6, true, true, false, false),
+            0x22 => (Controller::Mbc7, true, true, false, true),
+            0xFC => (Controller::PocketCamera, true, true, false, false),
+            0xFD => (Controller::Tama5, true, true, true, false),
+            0xFE => (Controller::HuC3, true, true, true, false),
+            0xFF => (Controller::HuC1, true, true, false, false),
+            _ => return None,
+        };
+
+        Some(Self {
+            code,
+            controller,
+            has_ram,
+            has_battery,
+            has_timer,
+            has_rumble,
+        })
+    }
+}
+
+/// The publisher of a cartridge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Licensee {
+    /// A one byte code from the original header layout.
+    Old(u8),
+    /// A two character ASCII code, used by cartridges released after the SGB.
+    New([u8; 2]),
+}
+
+impl fmt::Display for Licensee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Old(code) => write!(f, "{code:02X}"),
+            Self::New(code) => write!(f, "{}", String::from_utf8_lossy(code)),
+        }
+    }
+}
+
+/// The cartridge header at `0x100..=0x14F`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub title: String,
+    pub cgb_support: CgbSupport,
+    pub supports_sgb: bool,
+    pub cartridge_type: CartridgeType,
+    /// The ROM size in bytes.
+    pub rom_size: usize,
+    /// The external RAM size in bytes, not counting RAM built into the controller.
+    pub ram_size: usize,
+    pub licensee: Licensee,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl Header {
+    /// Parses the header of `rom`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `rom` is too small to hold a header, or if the header declares an
+    /// unknown cartridge type, ROM size or RAM size.
+    pub fn parse(rom: &[u8]) -> Result<Self, CartridgeError> {
+        if rom.len() < HEADER_END {
+            return Err(CartridgeError::TooSmall { size: rom.len() });
+        }
+
+        let cgb_support = match rom[CGB_FLAG_ADDRESS] {
+            0xC0 => CgbSupport::Required,
+            flag if flag & 0x80 != 0 => CgbSupport::Enhanced,
+            _ => CgbSupport::None,
+        };
+
+        // Newer cartridges use the last bytes of the title for the CGB flag.
+        let title_end = if cgb_support == CgbSupport::None {
+            TITLE_END
+        } else {
+            CGB_FLAG_ADDRESS - 1
+        };
+        let title = rom[TITLE_START..=title_end]
+            .iter()
+            .take_while(|&&byte| byte != 0)
+            .map(|&byte| char::from(byte))
+            .collect::<String>()
+            .trim_end()
+            .to_owned();
+
+        let cartridge_type_code = rom[CARTRIDGE_TYPE_ADDRESS];
+        let cartridge_type = CartridgeType::from_code(cartridge_type_code)
+            .ok_or(CartridgeError::UnknownCartridgeType(cartridge_type_code))?;
+
+        let rom_size_code = rom[ROM_SIZE_ADDRESS];
+        if rom_size_code > 0x08 {
+            return Err(CartridgeError::UnknownRomSize(rom_size_code));
+        }
+        let rom_size = 0x8000 << rom_size_code;
+
+        let ram_size_code = rom[RAM_SIZE_ADDRESS];
+        let ram_size = match ram_size_code {
+            0x00 => 0,
+            0x01 => 0x800,
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x2_0000,
+            0x05 => 0x1_0000,
+            _ => return Err(CartridgeError::UnknownRamSize(ram_size_code)),
+        };
+
+        let licensee = match rom[OLD_LICENSEE_CODE_ADDRESS] {
+            USE_NEW_LICENSEE_CODE => Licensee::New([
+                rom[NEW_LICENSEE_CODE_ADDRESS],
+                rom[NEW_LICENSEE_CODE_ADDRESS + 1],
+            ]),
+            code => Licensee::Old(code),
+        };
+
+        Ok(Self {
+            title,
+            cgb_support,
+            supports_sgb: rom[SGB_FLAG_ADDRESS] == 0x03,
+            cartridge_type,
+            rom_size,
+            ram_size,
+            licensee,
+            version: rom[VERSION_ADDRESS],
+            header_checksum: rom[HEADER_CHECKSUM_ADDRESS],
+            global_checksum: u16::from_be_bytes([
+                rom[GLOBAL_CHECKSUM_ADDRESS],
+                rom[GLOBAL_CHECKSUM_ADDRESS + 1],
+            ]),
+        })
+    }
+
+    /// Computes the checksum over `0x134..=0x14C` that the boot ROM verifies.
+    pub fn compute_header_checksum(rom: &[u8]) -> u8 {
+        rom[TITLE_START..HEADER_CHECKSUM_ADDRESS]
+            .iter()
+            .fold(0u8, |checksum, &byte| {
+                checksum.wrapping_sub(byte).wrapping_sub(1)
+            })
+    }
+
+    /// Computes the sum of every byte in `rom` except the global checksum itself.
+    pub fn compute_global_checksum(rom: &[u8]) -> u16 {
+        rom.iter()
+            .enumerate()
+            .filter(|&(address, _)| {
+                address != GLOBAL_CHECKSUM_ADDRESS && address != GLOBAL_CHECKSUM_ADDRESS + 1
+            })
+            .fold(0u16, |checksum, (_, &byte)| {
+                checksum.wrapping_add(u16::from(byte))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::cartridge::Cartridge;
+
+    /// Returns a 64 KiB ROM with a CGB-enhanced MBC3 header and both checksums filled in.
+    fn rom_with_header() -> Vec<u8> {
+        let mut rom = vec![0; 0x1_0000];
+        rom[TITLE_START..TITLE_START + 7].copy_from_slice(b"POKEMON");
+        rom[CGB_FLAG_ADDRESS] = 0x80;
+        rom[NEW_LICENSEE_CODE_ADDRESS..NEW_LICENSEE_CODE_ADDRESS + 2].copy_from_slice(b"01");
+        rom[SGB_FLAG_ADDRESS] = 0x03;
+        rom[CARTRIDGE_TYPE_ADDRESS] = 0x10;
+        rom[ROM_SIZE_ADDRESS] = 0x01;
+        rom[RAM_SIZE_ADDRESS] = 0x03;
+        rom[OLD_LICENSEE_CODE_ADDRESS] = USE_NEW_LICENSEE_CODE;
+        rom[VERSION_ADDRESS] = 0x02;
+        rom[HEADER_CHECKSUM_ADDRESS] = Header::compute_header_checksum(&rom);
+        let global_checksum = Header::compute_global_checksum(&rom);
+        rom[GLOBAL_CHECKSUM_ADDRESS..GLOBAL_CHECKSUM_ADDRESS + 2]
+            .copy_from_slice(&global_checksum.to_be_bytes());
+
+        rom
+    }
+
+    #[test]
+    fn parses_header_fields() {
+        let header = Header::parse(&rom_with_header()).unwrap();
+
+        assert_eq!(header.title, "POKEMON");
+        assert_eq!(header.cgb_support, CgbSupport::Enhanced);
+        assert!(header.supports_sgb);
+        assert_eq!(header.cartridge_type.controller, Controller::Mbc3);
+        assert!(header.cartridge_type.has_timer);
+        assert!(header.cartridge_type.has_battery);
+        assert_eq!(header.rom_size, 0x1_0000);
+        assert_eq!(header.ram_size, 0x8000);
+        assert_eq!(header.licensee, Licensee::New(*b"01"));
+        assert_eq!(header.version, 0x02);
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        assert!(matches!(
+            Header::parse(&[0; HEADER_END - 1]),
+            Err(CartridgeError::TooSmall { size }) if size == HEADER_END - 1
+        ));
+
+        let with_byte = |address: usize, value: u8| {
+            let mut rom = rom_with_header();
+            rom[address] = value;
+
+            Header::parse(&rom)
+        };
+        assert!(matches!(
+            with_byte(CARTRIDGE_TYPE_ADDRESS, 0x04),
+            Err(CartridgeError::UnknownCartridgeType(0x04))
+        ));
+        assert!(matches!(
+            with_byte(ROM_SIZE_ADDRESS, 0x09),
+            Err(CartridgeError::UnknownRomSize(0x09))
+        ));
+        assert!(matches!(
+            with_byte(RAM_SIZE_ADDRESS, 0x06),
+            Err(CartridgeError::UnknownRamSize(0x06))
+        ));
+    }
+
+    #[test]
+    fn computes_checksums() {
+        // Every one of the 25 header bytes takes off its value plus one.
+        let mut rom = vec![0; 0x8000];
+        assert_eq!(Header::compute_header_checksum(&rom), 0xE7);
+
+        rom[TITLE_START] = 0x10;
+        rom[0x7FFF] = 0xF0;
+        rom[GLOBAL_CHECKSUM_ADDRESS] = 0xAA;
+        rom[GLOBAL_CHECKSUM_ADDRESS + 1] = 0xBB;
+        assert_eq!(Header::compute_header_checksum(&rom), 0xD7);
+        assert_eq!(Header::compute_global_checksum(&rom), 0x100);
+    }
+
+    #[test]
+    fn verifies_header_checksum_before_global_checksum() {
+        let cartridge = Cartridge::new(rom_with_header()).unwrap();
+        assert!(cartridge.verify_checksums().is_ok());
+
+        let mut rom = rom_with_header();
+        rom[0x4000] = 0x01;
+        assert!(matches!(
+            Cartridge::new(rom.clone()).unwrap().verify_checksums(),
+            Err(CartridgeError::GlobalChecksumMismatch { .. })
+        ));
+
+        rom[VERSION_ADDRESS] = 0x03;
+        assert!(matches!(
+            Cartridge::new(rom).unwrap().verify_checksums(),
+            Err(CartridgeError::HeaderChecksumMismatch { .. })
+        ));
+    }
+}
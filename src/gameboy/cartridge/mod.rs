@@ -0,0 +1,194 @@
+pub mod header;
+pub mod save;
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use self::{
+    header::{Controller, Header},
+    save::BatterySave,
+};
+use super::{
+    mbc::{
+        rtc::{Rtc, SystemClock},
+        Mbc, Mbc1, Mbc2, Mbc3, Mbc5, RomOnly,
+    },
+    memory::MemoryBus,
+    model::Model,
+};
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    /// The ROM is too small to contain a header.
+    TooSmall {
+        size: usize,
+    },
+    UnknownCartridgeType(u8),
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+    HeaderChecksumMismatch {
+        expected: u8,
+        actual: u8,
+    },
+    GlobalChecksumMismatch {
+        expected: u16,
+        actual: u16,
+    },
+    /// The cartridge uses a controller that isn't emulated.
+    UnsupportedController(Controller),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Failed to read the cartridge: {error}"),
+            Self::TooSmall { size } => {
+                write!(
+                    f,
+                    "The ROM is too small to contain a header! ({size} bytes)"
+                )
+            }
+            Self::UnknownCartridgeType(code) => write!(f, "Unknown cartridge type 0x{code:02X}!"),
+            Self::UnknownRomSize(code) => write!(f, "Unknown ROM size 0x{code:02X}!"),
+            Self::UnknownRamSize(code) => write!(f, "Unknown RAM size 0x{code:02X}!"),
+            Self::HeaderChecksumMismatch { expected, actual } => write!(
+                f,
+                "Header checksum mismatch! (expected 0x{expected:02X}, found 0x{actual:02X})"
+            ),
+            Self::GlobalChecksumMismatch { expected, actual } => write!(
+                f,
+                "Global checksum mismatch! (expected 0x{expected:04X}, found 0x{actual:04X})"
+            ),
+            Self::UnsupportedController(controller) => {
+                write!(f, "Unsupported cartridge controller {controller:?}!")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// A cartridge ROM image along with its parsed header.
+#[derive(Debug)]
+pub struct Cartridge {
+    header: Header,
+    rom: Vec<u8>,
+    /// The file the ROM was loaded from, if any.
+    path: Option<PathBuf>,
+}
+
+impl Cartridge {
+    /// Parses the header of `rom`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header can't be parsed.
+    pub fn new(rom: Vec<u8>) -> Result<Self, CartridgeError> {
+        let header = Header::parse(&rom)?;
+
+        Ok(Self {
+            header,
+            rom,
+            path: None,
+        })
+    }
+
+    /// Reads and parses a `.gb` or `.gbc` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or its header can't be parsed.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, CartridgeError> {
+        let path = path.as_ref();
+
+        let mut cartridge = Self::new(fs::read(path)?)?;
+        cartridge.path = Some(path.to_owned());
+
+        Ok(cartridge)
+    }
+
+    pub const fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Returns the `.sav` file next to the ROM if the cartridge has a battery, or `None` if it
+    /// doesn't or wasn't loaded from a file.
+    pub fn battery_save(&self) -> Option<BatterySave> {
+        if !self.header.cartridge_type.has_battery {
+            return None;
+        }
+
+        self.path
+            .as_ref()
+            .map(|path| BatterySave::new(path.with_extension("sav")))
+    }
+
+    /// Checks the header checksum and the global checksum against the ROM's contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the first checksum that doesn't match.
+    pub fn verify_checksums(&self) -> Result<(), CartridgeError> {
+        let actual = Header::compute_header_checksum(&self.rom);
+        if actual != self.header.header_checksum {
+            return Err(CartridgeError::HeaderChecksumMismatch {
+                expected: self.header.header_checksum,
+                actual,
+            });
+        }
+
+        let actual = Header::compute_global_checksum(&self.rom);
+        if actual != self.header.global_checksum {
+            return Err(CartridgeError::GlobalChecksumMismatch {
+                expected: self.header.global_checksum,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Builds a memory bus with the cartridge mapped in through its controller. A real-time
+    /// clock runs off the system clock, which [`Rtc::set_clock`] can replace.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cartridge's controller isn't emulated.
+    pub fn into_memory_bus(self, model: Model) -> Result<MemoryBus, CartridgeError> {
+        let ram = vec![0; self.header.ram_size];
+        let cartridge_type = self.header.cartridge_type;
+
+        let mbc: Box<dyn Mbc> = match cartridge_type.controller {
+            Controller::RomOnly => Box::new(RomOnly::new(self.rom, ram)),
+            Controller::Mbc1 => Box::new(Mbc1::new(self.rom, ram)),
+            Controller::Mbc2 => Box::new(Mbc2::new(self.rom)),
+            Controller::Mbc3 => {
+                let rtc = cartridge_type
+                    .has_timer
+                    .then(|| Rtc::new(Box::new(SystemClock)));
+
+                Box::new(Mbc3::new(self.rom, ram, rtc))
+            }
+            Controller::Mbc5 => Box::new(Mbc5::new(self.rom, ram, cartridge_type.has_rumble)),
+            controller => return Err(CartridgeError::UnsupportedController(controller)),
+        };
+
+        Ok(MemoryBus::new(mbc, model))
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::gameboy::memory::MemoryBus;
+
+/// Battery-backed cartridge RAM, kept in a `.sav` file next to the ROM.
+///
+/// The file holds the raw RAM contents, followed by the 48-byte RTC block on cartridges with a
+/// real-time clock. This is the layout most other emulators use, so saves can be moved between
+/// them.
+#[derive(Debug)]
+pub struct BatterySave {
+    path: PathBuf,
+    /// The file's contents as of the last load or flush, to avoid rewriting it unchanged.
+    saved: Vec<u8>,
+}
+
+impl BatterySave {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            saved: Vec::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the save file into the cartridge on `bus`, returning `false` if there is no save
+    /// file yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the save file exists but can't be read.
+    pub fn load(&mut self, bus: &mut MemoryBus) -> io::Result<bool> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(error),
+        };
+
+        let ram = bus.cartridge_ram_mut();
+        let ram_size = ram.len().min(data.len());
+        ram[..ram_size].copy_from_slice(&data[..ram_size]);
+
+        if let Some(rtc) = bus.rtc_mut() {
+            rtc.load_state(&data[ram_size..]);
+        }
+
+        self.saved = data;
+
+        Ok(true)
+    }
+
+    /// Writes the cartridge RAM on `bus` to the save file if it changed since the last load or
+    /// flush, returning whether anything was written.
+    ///
+    /// The data goes to a temporary file that then replaces the save file, so a crash midway
+    /// never leaves a truncated save behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the save file can't be written.
+    pub fn flush(&mut self, bus: &MemoryBus) -> io::Result<bool> {
+        let mut data = bus.cartridge_ram().to_vec();
+        if let Some(rtc) = bus.rtc() {
+            data.extend_from_slice(&rtc.save_state());
+        }
+
+        if data == self.saved {
+            return Ok(false);
+        }
+
+        let temporary_path = self.path.with_extension("sav.tmp");
+        let mut file = fs::File::create(&temporary_path)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&temporary_path, &self.path)?;
+
+        self.saved = data;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+    use crate::gameboy::{
+        mbc::{
+            rtc::{ManualClock, Rtc, RtcRegister, RTC_STATE_SIZE},
+            Mbc3,
+        },
+        model::Model,
+    };
+
+    const RAM_SIZE: usize = 0x2000;
+
+    fn mbc3_bus_at(seconds: u64) -> MemoryBus {
+        let rtc = Rtc::new(Box::new(ManualClock::new(seconds)));
+        let mbc = Mbc3::new(vec![0; 0x8000], vec![0; RAM_SIZE], Some(rtc));
+
+        MemoryBus::new(Box::new(mbc), Model::Dmg)
+    }
+
+    fn temporary_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("gameboy_rs-{}-{name}.sav", process::id()))
+    }
+
+    /// Loads `path` into an MBC3 cartridge at `seconds` and checks it got the RAM and the clock
+    /// saved at 1000 seconds.
+    fn assert_loads_saved_state(path: &Path, seconds: u64) {
+        let mut bus = mbc3_bus_at(seconds);
+        assert!(BatterySave::new(path).load(&mut bus).unwrap());
+
+        assert_eq!(bus.cartridge_ram()[0], 0xAB);
+        assert_eq!(bus.cartridge_ram()[RAM_SIZE - 1], 0xCD);
+
+        let rtc = bus.rtc_mut().unwrap();
+        rtc.latch();
+        assert_eq!(rtc.read(RtcRegister::Minutes), 7);
+        assert_eq!(
+            u64::from(rtc.read(RtcRegister::Seconds)),
+            seconds - 1_000,
+            "the time passed since saving is caught up on"
+        );
+    }
+
+    #[test]
+    fn round_trips_ram_and_rtc() {
+        let path = temporary_path("round-trip");
+        let legacy_path = temporary_path("round-trip-legacy");
+
+        let mut bus = mbc3_bus_at(1_000);
+        bus.cartridge_ram_mut()[0] = 0xAB;
+        bus.cartridge_ram_mut()[RAM_SIZE - 1] = 0xCD;
+        bus.rtc_mut().unwrap().write(RtcRegister::Minutes, 7);
+
+        let mut save = BatterySave::new(&path);
+        assert!(save.flush(&bus).unwrap());
+        assert!(!save.flush(&bus).unwrap(), "unchanged data isn't rewritten");
+
+        let data = fs::read(&path).unwrap();
+        assert_eq!(data.len(), RAM_SIZE + RTC_STATE_SIZE);
+        assert_loads_saved_state(&path, 1_030);
+
+        // The older 44-byte trailer cuts the timestamp down to 32 bits.
+        fs::write(&legacy_path, &data[..data.len() - 4]).unwrap();
+        assert_loads_saved_state(&legacy_path, 1_030);
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(legacy_path).unwrap();
+    }
+
+    #[test]
+    fn missing_file_loads_nothing() {
+        let mut bus = mbc3_bus_at(0);
+
+        assert!(!BatterySave::new(temporary_path("missing"))
+            .load(&mut bus)
+            .unwrap());
+    }
+}
@@ -1,9 +1,21 @@
+use std::fmt;
+
+use super::{
+    cartridge::header::{
+        HEADER_CHECKSUM_ADDRESS, NEW_LICENSEE_CODE_ADDRESS, OLD_LICENSEE_CODE_ADDRESS, TITLE_START,
+    },
+    interrupt::Interrupt,
+    memory::MemoryBus,
+    model::Model,
+    ppu::DOTS_PER_FRAME,
+};
+
 const ZERO_FLAG_BYTE_POSITION: u8 = 7;
 const SUBTRACT_FLAG_BYTE_POSITION: u8 = 6;
 const HALF_CARRY_FLAG_BYTE_POSITION: u8 = 5;
 const CARRY_FLAG_BYTE_POSITION: u8 = 4;
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, Copy)]
 struct FlagsRegister {
     zero: bool,
     subtract: bool,
@@ -36,7 +48,7 @@ impl std::convert::From<u8> for FlagsRegister {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Registers {
     a: u8,
     b: u8,
@@ -49,6 +61,18 @@ pub struct Registers {
 }
 
 impl Registers {
+    /// Returns `AF`. The low nibble of `F` always reads as zero since only the four flags are
+    /// stored.
+    pub fn get_af(&self) -> u16 {
+        (self.a as u16) << 8 | u16::from(u8::from(self.f))
+    }
+
+    /// Sets `AF`, discarding the low nibble of `F`.
+    pub fn set_af(&mut self, value: u16) {
+        self.a = ((value & 0xFF00) >> 8) as u8;
+        self.f = FlagsRegister::from((value & 0xFF) as u8);
+    }
+
     pub const fn get_bc(&self) -> u16 {
         (self.b as u16) << 8 | self.c as u16
     }
@@ -75,6 +99,124 @@ impl Registers {
         self.h = ((value & 0xFF00) >> 8) as u8;
         self.l = (value & 0xFF) as u8;
     }
+
+    /// Returns the registers as the model's boot ROM leaves them, some of which depend on the
+    /// cartridge header.
+    fn post_boot(bus: &MemoryBus) -> Self {
+        let header_byte = |address: usize| bus.read_byte(address as u16);
+        let model = bus.model();
+
+        match model {
+            Model::Dmg0 => Self {
+                a: 0x01,
+                b: 0xFF,
+                c: 0x13,
+                d: 0x00,
+                e: 0xC1,
+                f: FlagsRegister::default(),
+                h: 0x84,
+                l: 0x03,
+            },
+            Model::Dmg | Model::Mgb => {
+                // The boot ROM's header check leaves the half-carry and carry flags set unless
+                // the checksum happens to be zero.
+                let checksum_carry = header_byte(HEADER_CHECKSUM_ADDRESS) != 0;
+
+                Self {
+                    a: if model == Model::Mgb { 0xFF } else { 0x01 },
+                    b: 0x00,
+                    c: 0x13,
+                    d: 0x00,
+                    e: 0xD8,
+                    f: FlagsRegister {
+                        zero: true,
+                        subtract: false,
+                        half_carry: checksum_carry,
+                        carry: checksum_carry,
+                    },
+                    h: 0x01,
+                    l: 0x4D,
+                }
+            }
+            Model::Sgb | Model::Sgb2 => Self {
+                a: if model == Model::Sgb2 { 0xFF } else { 0x01 },
+                b: 0x00,
+                c: 0x14,
+                d: 0x00,
+                e: 0x00,
+                f: FlagsRegister::default(),
+                h: 0xC0,
+                l: 0x60,
+            },
+            Model::Cgb | Model::Agb => {
+                let mut registers = if bus.is_cgb_mode() {
+                    Self {
+                        a: 0x11,
+                        b: 0x00,
+                        c: 0x00,
+                        d: 0xFF,
+                        e: 0x56,
+                        f: FlagsRegister {
+                            zero: true,
+                            ..FlagsRegister::default()
+                        },
+                        h: 0x00,
+                        l: 0x0D,
+                    }
+                } else {
+                    // In DMG compatibility mode the boot ROM sums the title of
+                    // Nintendo-published games to pick a colorization palette, and leaves the sum
+                    // in `B`.
+                    let is_nintendo = match header_byte(OLD_LICENSEE_CODE_ADDRESS) {
+                        0x01 => true,
+                        0x33 => {
+                            header_byte(NEW_LICENSEE_CODE_ADDRESS) == b'0'
+                                && header_byte(NEW_LICENSEE_CODE_ADDRESS + 1) == b'1'
+                        }
+                        _ => false,
+                    };
+                    let title_sum = if is_nintendo {
+                        (TITLE_START..TITLE_START + 16)
+                            .fold(0u8, |sum, address| sum.wrapping_add(header_byte(address)))
+                    } else {
+                        0x00
+                    };
+                    let hl: u16 = if matches!(title_sum, 0x43 | 0x58) {
+                        0x991A
+                    } else {
+                        0x007C
+                    };
+
+                    Self {
+                        a: 0x11,
+                        b: title_sum,
+                        c: 0x00,
+                        d: 0x00,
+                        e: 0x08,
+                        f: FlagsRegister {
+                            zero: true,
+                            ..FlagsRegister::default()
+                        },
+                        h: (hl >> 8) as u8,
+                        l: (hl & 0xFF) as u8,
+                    }
+                };
+
+                // The AGB's boot ROM ends with an extra `INC B`, which games use to detect it.
+                if model == Model::Agb {
+                    registers.b = registers.b.wrapping_add(1);
+                    registers.f = FlagsRegister {
+                        zero: registers.b == 0,
+                        subtract: false,
+                        half_carry: registers.b & 0x0F == 0,
+                        carry: registers.f.carry,
+                    };
+                }
+
+                registers
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -86,17 +228,60 @@ enum ArithmeticTarget {
     E,
     H,
     L,
+    Hli,
+    D8,
 }
 
 #[derive(Debug)]
 enum IncDecTarget {
+    A,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    Hli,
     BC,
     DE,
+    HL,
+    SP,
+}
+
+#[derive(Debug)]
+enum AddHlTarget {
+    BC,
+    DE,
+    HL,
+    SP,
 }
 
 #[derive(Debug)]
 enum PrefixTarget {
+    A,
     B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    Hli,
+}
+
+impl PrefixTarget {
+    /// Decodes the operand encoded in the lowest three bits of a prefixed opcode.
+    const fn from_byte(byte: u8) -> Self {
+        match byte & 0b111 {
+            0 => Self::B,
+            1 => Self::C,
+            2 => Self::D,
+            3 => Self::E,
+            4 => Self::H,
+            5 => Self::L,
+            6 => Self::Hli,
+            _ => Self::A,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -124,9 +309,56 @@ enum LoadByteSource {
     Hli,
 }
 
+#[derive(Debug)]
+enum LoadWordTarget {
+    BC,
+    DE,
+    HL,
+    SP,
+}
+
+/// A memory operand that is loaded into or stored from the `A` register.
+#[derive(Debug)]
+enum Indirect {
+    /// `(BC)`
+    BC,
+    /// `(DE)`
+    DE,
+    /// `(HL+)`, incrementing `HL` after the access.
+    HLIncrement,
+    /// `(HL-)`, decrementing `HL` after the access.
+    HLDecrement,
+    /// `(a16)`, an absolute address following the opcode.
+    Word,
+    /// `(C)`, the address `0xFF00 + C`.
+    HighC,
+}
+
 #[derive(Debug)]
 enum LoadType {
     Byte(LoadByteTarget, LoadByteSource),
+    Word(LoadWordTarget),
+    AFromIndirect(Indirect),
+    IndirectFromA(Indirect),
+    /// `LDH A, (a8)`
+    AFromByteAddress,
+    /// `LDH (a8), A`
+    ByteAddressFromA,
+    /// `LD (a16), SP`
+    IndirectFromSp,
+    /// `LD SP, HL`
+    SpFromHl,
+    /// `LD HL, SP + e8`
+    HlFromSpOffset,
+}
+
+/// A register pair that can be pushed onto or popped off the stack.
+#[derive(Debug)]
+enum StackTarget {
+    AF,
+    BC,
+    DE,
+    HL,
 }
 
 #[derive(Debug)]
@@ -140,168 +372,1799 @@ enum JumpTest {
 
 #[derive(Debug)]
 enum Instruction {
+    Nop,
+    Stop,
+    Halt,
+    Di,
+    Ei,
     Add(ArithmeticTarget),
+    Adc(ArithmeticTarget),
+    Sub(ArithmeticTarget),
+    Sbc(ArithmeticTarget),
+    And(ArithmeticTarget),
+    Xor(ArithmeticTarget),
+    Or(ArithmeticTarget),
+    Cp(ArithmeticTarget),
+    AddHl(AddHlTarget),
+    AddSp,
     Inc(IncDecTarget),
+    Dec(IncDecTarget),
+    Rlca,
+    Rrca,
+    Rla,
+    Rra,
+    Daa,
+    Cpl,
+    Scf,
+    Ccf,
     Rlc(PrefixTarget),
+    Rrc(PrefixTarget),
+    Rl(PrefixTarget),
+    Rr(PrefixTarget),
+    Sla(PrefixTarget),
+    Sra(PrefixTarget),
+    Swap(PrefixTarget),
+    Srl(PrefixTarget),
+    Bit(u8, PrefixTarget),
+    Res(u8, PrefixTarget),
+    Set(u8, PrefixTarget),
     Jp(JumpTest),
+    JpHl,
+    Jr(JumpTest),
+    Call(JumpTest),
+    Ret(JumpTest),
+    Reti,
+    /// Calls one of the eight fixed vectors at `0x00`, `0x08`, ..., `0x38`.
+    Rst(u8),
+    Push(StackTarget),
+    Pop(StackTarget),
     Ld(LoadType),
 }
 
 impl Instruction {
     const fn from_byte(byte: u8, is_prefixed: bool) -> Option<Self> {
         if is_prefixed {
-            Self::from_prefixed_byte(byte)
+            Some(Self::from_prefixed_byte(byte))
         } else {
             Self::from_normal_byte(byte)
         }
     }
 
-    const fn from_prefixed_byte(byte: u8) -> Option<Self> {
+    /// Decodes the opcode following a `0xCB` prefix. Every one of the 256 opcodes is valid.
+    const fn from_prefixed_byte(byte: u8) -> Self {
+        let target = PrefixTarget::from_byte(byte);
+        let bit = (byte >> 3) & 0b111;
+
         match byte {
-            0x00 => Some(Self::Rlc(PrefixTarget::B)),
-            _ => None,
+            0x00..=0x07 => Self::Rlc(target),
+            0x08..=0x0F => Self::Rrc(target),
+            0x10..=0x17 => Self::Rl(target),
+            0x18..=0x1F => Self::Rr(target),
+            0x20..=0x27 => Self::Sla(target),
+            0x28..=0x2F => Self::Sra(target),
+            0x30..=0x37 => Self::Swap(target),
+            0x38..=0x3F => Self::Srl(target),
+            0x40..=0x7F => Self::Bit(bit, target),
+            0x80..=0xBF => Self::Res(bit, target),
+            0xC0..=0xFF => Self::Set(bit, target),
         }
     }
 
+    /// Decodes an unprefixed opcode, returning `None` for the 11 opcodes that are illegal on the
+    /// SM83.
     const fn from_normal_byte(byte: u8) -> Option<Self> {
         let instruction = match byte {
-            0x02 => Self::Inc(IncDecTarget::BC),
+            0x00 => Self::Nop,
+            0x01 => Self::Ld(LoadType::Word(LoadWordTarget::BC)),
+            0x02 => Self::Ld(LoadType::IndirectFromA(Indirect::BC)),
+            0x03 => Self::Inc(IncDecTarget::BC),
+            0x04 => Self::Inc(IncDecTarget::B),
+            0x05 => Self::Dec(IncDecTarget::B),
+            0x06 => Self::Ld(LoadType::Byte(LoadByteTarget::B, LoadByteSource::D8)),
+            0x07 => Self::Rlca,
+            0x08 => Self::Ld(LoadType::IndirectFromSp),
+            0x09 => Self::AddHl(AddHlTarget::BC),
+            0x0A => Self::Ld(LoadType::AFromIndirect(Indirect::BC)),
+            0x0B => Self::Dec(IncDecTarget::BC),
+            0x0C => Self::Inc(IncDecTarget::C),
+            0x0D => Self::Dec(IncDecTarget::C),
+            0x0E => Self::Ld(LoadType::Byte(LoadByteTarget::C, LoadByteSource::D8)),
+            0x0F => Self::Rrca,
+
+            0x10 => Self::Stop,
+            0x11 => Self::Ld(LoadType::Word(LoadWordTarget::DE)),
+            0x12 => Self::Ld(LoadType::IndirectFromA(Indirect::DE)),
             0x13 => Self::Inc(IncDecTarget::DE),
-            _ => return None,
+            0x14 => Self::Inc(IncDecTarget::D),
+            0x15 => Self::Dec(IncDecTarget::D),
+            0x16 => Self::Ld(LoadType::Byte(LoadByteTarget::D, LoadByteSource::D8)),
+            0x17 => Self::Rla,
+            0x18 => Self::Jr(JumpTest::Always),
+            0x19 => Self::AddHl(AddHlTarget::DE),
+            0x1A => Self::Ld(LoadType::AFromIndirect(Indirect::DE)),
+            0x1B => Self::Dec(IncDecTarget::DE),
+            0x1C => Self::Inc(IncDecTarget::E),
+            0x1D => Self::Dec(IncDecTarget::E),
+            0x1E => Self::Ld(LoadType::Byte(LoadByteTarget::E, LoadByteSource::D8)),
+            0x1F => Self::Rra,
+
+            0x20 => Self::Jr(JumpTest::NotZero),
+            0x21 => Self::Ld(LoadType::Word(LoadWordTarget::HL)),
+            0x22 => Self::Ld(LoadType::IndirectFromA(Indirect::HLIncrement)),
+            0x23 => Self::Inc(IncDecTarget::HL),
+            0x24 => Self::Inc(IncDecTarget::H),
+            0x25 => Self::Dec(IncDecTarget::H),
+            0x26 => Self::Ld(LoadType::Byte(LoadByteTarget::H, LoadByteSource::D8)),
+            0x27 => Self::Daa,
+            0x28 => Self::Jr(JumpTest::Zero),
+            0x29 => Self::AddHl(AddHlTarget::HL),
+            0x2A => Self::Ld(LoadType::AFromIndirect(Indirect::HLIncrement)),
+            0x2B => Self::Dec(IncDecTarget::HL),
+            0x2C => Self::Inc(IncDecTarget::L),
+            0x2D => Self::Dec(IncDecTarget::L),
+            0x2E => Self::Ld(LoadType::Byte(LoadByteTarget::L, LoadByteSource::D8)),
+            0x2F => Self::Cpl,
+
+            0x30 => Self::Jr(JumpTest::NotCarry),
+            0x31 => Self::Ld(LoadType::Word(LoadWordTarget::SP)),
+            0x32 => Self::Ld(LoadType::IndirectFromA(Indirect::HLDecrement)),
+            0x33 => Self::Inc(IncDecTarget::SP),
+            0x34 => Self::Inc(IncDecTarget::Hli),
+            0x35 => Self::Dec(IncDecTarget::Hli),
+            0x36 => Self::Ld(LoadType::Byte(LoadByteTarget::Hli, LoadByteSource::D8)),
+            0x37 => Self::Scf,
+            0x38 => Self::Jr(JumpTest::Carry),
+            0x39 => Self::AddHl(AddHlTarget::SP),
+            0x3A => Self::Ld(LoadType::AFromIndirect(Indirect::HLDecrement)),
+            0x3B => Self::Dec(IncDecTarget::SP),
+            0x3C => Self::Inc(IncDecTarget::A),
+            0x3D => Self::Dec(IncDecTarget::A),
+            0x3E => Self::Ld(LoadType::Byte(LoadByteTarget::A, LoadByteSource::D8)),
+            0x3F => Self::Ccf,
+
+            0x40 => Self::Ld(LoadType::Byte(LoadByteTarget::B, LoadByteSource::B)),
+            0x41 => Self::Ld(LoadType::Byte(LoadByteTarget::B, LoadByteSource::C)),
+            0x42 => Self::Ld(LoadType::Byte(LoadByteTarget::B, LoadByteSource::D)),
+            0x43 => Self::Ld(LoadType::Byte(LoadByteTarget::B, LoadByteSource::E)),
+            0x44 => Self::Ld(LoadType::Byte(LoadByteTarget::B, LoadByteSource::H)),
+            0x45 => Self::Ld(LoadType::Byte(LoadByteTarget::B, LoadByteSource::L)),
+            0x46 => Self::Ld(LoadType::Byte(LoadByteTarget::B, LoadByteSource::Hli)),
+            0x47 => Self::Ld(LoadType::Byte(LoadByteTarget::B, LoadByteSource::A)),
+            0x48 => Self::Ld(LoadType::Byte(LoadByteTarget::C, LoadByteSource::B)),
+            0x49 => Self::Ld(LoadType::Byte(LoadByteTarget::C, LoadByteSource::C)),
+            0x4A => Self::Ld(LoadType::Byte(LoadByteTarget::C, LoadByteSource::D)),
+            0x4B => Self::Ld(LoadType::Byte(LoadByteTarget::C, LoadByteSource::E)),
+            0x4C => Self::Ld(LoadType::Byte(LoadByteTarget::C, LoadByteSource::H)),
+            0x4D => Self::Ld(LoadType::Byte(LoadByteTarget::C, LoadByteSource::L)),
+            0x4E => Self::Ld(LoadType::Byte(LoadByteTarget::C, LoadByteSource::Hli)),
+            0x4F => Self::Ld(LoadType::Byte(LoadByteTarget::C, LoadByteSource::A)),
+
+            0x50 => Self::Ld(LoadType::Byte(LoadByteTarget::D, LoadByteSource::B)),
+            0x51 => Self::Ld(LoadType::Byte(LoadByteTarget::D, LoadByteSource::C)),
+            0x52 => Self::Ld(LoadType::Byte(LoadByteTarget::D, LoadByteSource::D)),
+            0x53 => Self::Ld(LoadType::Byte(LoadByteTarget::D, LoadByteSource::E)),
+            0x54 => Self::Ld(LoadType::Byte(LoadByteTarget::D, LoadByteSource::H)),
+            0x55 => Self::Ld(LoadType::Byte(LoadByteTarget::D, LoadByteSource::L)),
+            0x56 => Self::Ld(LoadType::Byte(LoadByteTarget::D, LoadByteSource::Hli)),
+            0x57 => Self::Ld(LoadType::Byte(LoadByteTarget::D, LoadByteSource::A)),
+            0x58 => Self::Ld(LoadType::Byte(LoadByteTarget::E, LoadByteSource::B)),
+            0x59 => Self::Ld(LoadType::Byte(LoadByteTarget::E, LoadByteSource::C)),
+            0x5A => Self::Ld(LoadType::Byte(LoadByteTarget::E, LoadByteSource::D)),
+            0x5B => Self::Ld(LoadType::Byte(LoadByteTarget::E, LoadByteSource::E)),
+            0x5C => Self::Ld(LoadType::Byte(LoadByteTarget::E, LoadByteSource::H)),
+            0x5D => Self::Ld(LoadType::Byte(LoadByteTarget::E, LoadByteSource::L)),
+            0x5E => Self::Ld(LoadType::Byte(LoadByteTarget::E, LoadByteSource::Hli)),
+            0x5F => Self::Ld(LoadType::Byte(LoadByteTarget::E, LoadByteSource::A)),
+
+            0x60 => Self::Ld(LoadType::Byte(LoadByteTarget::H, LoadByteSource::B)),
+            0x61 => Self::Ld(LoadType::Byte(LoadByteTarget::H, LoadByteSource::C)),
+            0x62 => Self::Ld(LoadType::Byte(LoadByteTarget::H, LoadByteSource::D)),
+            0x63 => Self::Ld(LoadType::Byte(LoadByteTarget::H, LoadByteSource::E)),
+            0x64 => Self::Ld(LoadType::Byte(LoadByteTarget::H, LoadByteSource::H)),
+            0x65 => Self::Ld(LoadType::Byte(LoadByteTarget::H, LoadByteSource::L)),
+            0x66 => Self::Ld(LoadType::Byte(LoadByteTarget::H, LoadByteSource::Hli)),
+            0x67 => Self::Ld(LoadType::Byte(LoadByteTarget::H, LoadByteSource::A)),
+            0x68 => Self::Ld(LoadType::Byte(LoadByteTarget::L, LoadByteSource::B)),
+            0x69 => Self::Ld(LoadType::Byte(LoadByteTarget::L, LoadByteSource::C)),
+            0x6A => Self::Ld(LoadType::Byte(LoadByteTarget::L, LoadByteSource::D)),
+            0x6B => Self::Ld(LoadType::Byte(LoadByteTarget::L, LoadByteSource::E)),
+            0x6C => Self::Ld(LoadType::Byte(LoadByteTarget::L, LoadByteSource::H)),
+            0x6D => Self::Ld(LoadType::Byte(LoadByteTarget::L, LoadByteSource::L)),
+            0x6E => Self::Ld(LoadType::Byte(LoadByteTarget::L, LoadByteSource::Hli)),
+            0x6F => Self::Ld(LoadType::Byte(LoadByteTarget::L, LoadByteSource::A)),
+
+            0x70 => Self::Ld(LoadType::Byte(LoadByteTarget::Hli, LoadByteSource::B)),
+            0x71 => Self::Ld(LoadType::Byte(LoadByteTarget::Hli, LoadByteSource::C)),
+            0x72 => Self::Ld(LoadType::Byte(LoadByteTarget::Hli, LoadByteSource::D)),
+            0x73 => Self::Ld(LoadType::Byte(LoadByteTarget::Hli, LoadByteSource::E)),
+            0x74 => Self::Ld(LoadType::Byte(LoadByteTarget::Hli, LoadByteSource::H)),
+            0x75 => Self::Ld(LoadType::Byte(LoadByteTarget::Hli, LoadByteSource::L)),
+            0x76 => Self::Halt,
+            0x77 => Self::Ld(LoadType::Byte(LoadByteTarget::Hli, LoadByteSource::A)),
+            0x78 => Self::Ld(LoadType::Byte(LoadByteTarget::A, LoadByteSource::B)),
+            0x79 => Self::Ld(LoadType::Byte(LoadByteTarget::A, LoadByteSource::C)),
+            0x7A => Self::Ld(LoadType::Byte(LoadByteTarget::A, LoadByteSource::D)),
+            0x7B => Self::Ld(LoadType::Byte(LoadByteTarget::A, LoadByteSource::E)),
+            0x7C => Self::Ld(LoadType::Byte(LoadByteTarget::A, LoadByteSource::H)),
+            0x7D => Self::Ld(LoadType::Byte(LoadByteTarget::A, LoadByteSource::L)),
+            0x7E => Self::Ld(LoadType::Byte(LoadByteTarget::A, LoadByteSource::Hli)),
+            0x7F => Self::Ld(LoadType::Byte(LoadByteTarget::A, LoadByteSource::A)),
+
+            0x80 => Self::Add(ArithmeticTarget::B),
+            0x81 => Self::Add(ArithmeticTarget::C),
+            0x82 => Self::Add(ArithmeticTarget::D),
+            0x83 => Self::Add(ArithmeticTarget::E),
+            0x84 => Self::Add(ArithmeticTarget::H),
+            0x85 => Self::Add(ArithmeticTarget::L),
+            0x86 => Self::Add(ArithmeticTarget::Hli),
+            0x87 => Self::Add(ArithmeticTarget::A),
+            0x88 => Self::Adc(ArithmeticTarget::B),
+            0x89 => Self::Adc(ArithmeticTarget::C),
+            0x8A => Self::Adc(ArithmeticTarget::D),
+            0x8B => Self::Adc(ArithmeticTarget::E),
+            0x8C => Self::Adc(ArithmeticTarget::H),
+            0x8D => Self::Adc(ArithmeticTarget::L),
+            0x8E => Self::Adc(ArithmeticTarget::Hli),
+            0x8F => Self::Adc(ArithmeticTarget::A),
+
+            0x90 => Self::Sub(ArithmeticTarget::B),
+            0x91 => Self::Sub(ArithmeticTarget::C),
+            0x92 => Self::Sub(ArithmeticTarget::D),
+            0x93 => Self::Sub(ArithmeticTarget::E),
+            0x94 => Self::Sub(ArithmeticTarget::H),
+            0x95 => Self::Sub(ArithmeticTarget::L),
+            0x96 => Self::Sub(ArithmeticTarget::Hli),
+            0x97 => Self::Sub(ArithmeticTarget::A),
+            0x98 => Self::Sbc(ArithmeticTarget::B),
+            0x99 => Self::Sbc(ArithmeticTarget::C),
+            0x9A => Self::Sbc(ArithmeticTarget::D),
+            0x9B => Self::Sbc(ArithmeticTarget::E),
+            0x9C => Self::Sbc(ArithmeticTarget::H),
+            0x9D => Self::Sbc(ArithmeticTarget::L),
+            0x9E => Self::Sbc(ArithmeticTarget::Hli),
+            0x9F => Self::Sbc(ArithmeticTarget::A),
+
+            0xA0 => Self::And(ArithmeticTarget::B),
+            0xA1 => Self::And(ArithmeticTarget::C),
+            0xA2 => Self::And(ArithmeticTarget::D),
+            0xA3 => Self::And(ArithmeticTarget::E),
+            0xA4 => Self::And(ArithmeticTarget::H),
+            0xA5 => Self::And(ArithmeticTarget::L),
+            0xA6 => Self::And(ArithmeticTarget::Hli),
+            0xA7 => Self::And(ArithmeticTarget::A),
+            0xA8 => Self::Xor(ArithmeticTarget::B),
+            0xA9 => Self::Xor(ArithmeticTarget::C),
+            0xAA => Self::Xor(ArithmeticTarget::D),
+            0xAB => Self::Xor(ArithmeticTarget::E),
+            0xAC => Self::Xor(ArithmeticTarget::H),
+            0xAD => Self::Xor(ArithmeticTarget::L),
+            0xAE => Self::Xor(ArithmeticTarget::Hli),
+            0xAF => Self::Xor(ArithmeticTarget::A),
+
+            0xB0 => Self::Or(ArithmeticTarget::B),
+            0xB1 => Self::Or(ArithmeticTarget::C),
+            0xB2 => Self::Or(ArithmeticTarget::D),
+            0xB3 => Self::Or(ArithmeticTarget::E),
+            0xB4 => Self::Or(ArithmeticTarget::H),
+            0xB5 => Self::Or(ArithmeticTarget::L),
+            0xB6 => Self::Or(ArithmeticTarget::Hli),
+            0xB7 => Self::Or(ArithmeticTarget::A),
+            0xB8 => Self::Cp(ArithmeticTarget::B),
+            0xB9 => Self::Cp(ArithmeticTarget::C),
+            0xBA => Self::Cp(ArithmeticTarget::D),
+            0xBB => Self::Cp(ArithmeticTarget::E),
+            0xBC => Self::Cp(ArithmeticTarget::H),
+            0xBD => Self::Cp(ArithmeticTarget::L),
+            0xBE => Self::Cp(ArithmeticTarget::Hli),
+            0xBF => Self::Cp(ArithmeticTarget::A),
+
+            0xC0 => Self::Ret(JumpTest::NotZero),
+            0xC1 => Self::Pop(StackTarget::BC),
+            0xC2 => Self::Jp(JumpTest::NotZero),
+            0xC3 => Self::Jp(JumpTest::Always),
+            0xC4 => Self::Call(JumpTest::NotZero),
+            0xC5 => Self::Push(StackTarget::BC),
+            0xC6 => Self::Add(ArithmeticTarget::D8),
+            0xC7 => Self::Rst(0x00),
+            0xC8 => Self::Ret(JumpTest::Zero),
+            0xC9 => Self::Ret(JumpTest::Always),
+            0xCA => Self::Jp(JumpTest::Zero),
+            0xCC => Self::Call(JumpTest::Zero),
+            0xCD => Self::Call(JumpTest::Always),
+            0xCE => Self::Adc(ArithmeticTarget::D8),
+            0xCF => Self::Rst(0x08),
+
+            0xD0 => Self::Ret(JumpTest::NotCarry),
+            0xD1 => Self::Pop(StackTarget::DE),
+            0xD2 => Self::Jp(JumpTest::NotCarry),
+            0xD4 => Self::Call(JumpTest::NotCarry),
+            0xD5 => Self::Push(StackTarget::DE),
+            0xD6 => Self::Sub(ArithmeticTarget::D8),
+            0xD7 => Self::Rst(0x10),
+            0xD8 => Self::Ret(JumpTest::Carry),
+            0xD9 => Self::Reti,
+            0xDA => Self::Jp(JumpTest::Carry),
+            0xDC => Self::Call(JumpTest::Carry),
+            0xDE => Self::Sbc(ArithmeticTarget::D8),
+            0xDF => Self::Rst(0x18),
+
+            0xE0 => Self::Ld(LoadType::ByteAddressFromA),
+            0xE1 => Self::Pop(StackTarget::HL),
+            0xE2 => Self::Ld(LoadType::IndirectFromA(Indirect::HighC)),
+            0xE5 => Self::Push(StackTarget::HL),
+            0xE6 => Self::And(ArithmeticTarget::D8),
+            0xE7 => Self::Rst(0x20),
+            0xE8 => Self::AddSp,
+            0xE9 => Self::JpHl,
+            0xEA => Self::Ld(LoadType::IndirectFromA(Indirect::Word)),
+            0xEE => Self::Xor(ArithmeticTarget::D8),
+            0xEF => Self::Rst(0x28),
+
+            0xF0 => Self::Ld(LoadType::AFromByteAddress),
+            0xF1 => Self::Pop(StackTarget::AF),
+            0xF2 => Self::Ld(LoadType::AFromIndirect(Indirect::HighC)),
+            0xF3 => Self::Di,
+            0xF5 => Self::Push(StackTarget::AF),
+            0xF6 => Self::Or(ArithmeticTarget::D8),
+            0xF7 => Self::Rst(0x30),
+            0xF8 => Self::Ld(LoadType::HlFromSpOffset),
+            0xF9 => Self::Ld(LoadType::SpFromHl),
+            0xFA => Self::Ld(LoadType::AFromIndirect(Indirect::Word)),
+            0xFB => Self::Ei,
+            0xFE => Self::Cp(ArithmeticTarget::D8),
+            0xFF => Self::Rst(0x38),
+
+            // 0xCB is the prefix byte and never reaches this decoder.
+            0xCB | 0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => {
+                return None
+            }
         };
 
         Some(instruction)
     }
 }
 
-#[derive(Debug)]
-struct MemoryBus {
-    memory: [u8; 0xFFFF],
+macro_rules! arithmetic_instruction {
+    ($self:expr, $target:expr, $operation:ident) => {{
+        let value = $self.read_arithmetic_target(&$target);
+        let new_value = $self.$operation(value);
+        $self.registers.a = new_value;
+
+        match $target {
+            ArithmeticTarget::D8 => ($self.pc.wrapping_add(2), 2),
+            ArithmeticTarget::Hli => ($self.pc.wrapping_add(1), 2),
+            _ => ($self.pc.wrapping_add(1), 1),
+        }
+    }};
 }
 
-impl MemoryBus {
-    const fn read_byte(&self, address: u16) -> u8 {
-        self.memory[address as usize]
-    }
+macro_rules! inc_dec_instruction {
+    ($self:expr, $target:expr, $operation:ident, $word_operation:ident) => {{
+        let cycles = match $target {
+            IncDecTarget::A => {
+                $self.registers.a = $self.$operation($self.registers.a);
+
+                1
+            }
+            IncDecTarget::B => {
+                $self.registers.b = $self.$operation($self.registers.b);
+
+                1
+            }
+            IncDecTarget::C => {
+                $self.registers.c = $self.$operation($self.registers.c);
+
+                1
+            }
+            IncDecTarget::D => {
+                $self.registers.d = $self.$operation($self.registers.d);
+
+                1
+            }
+            IncDecTarget::E => {
+                $self.registers.e = $self.$operation($self.registers.e);
+
+                1
+            }
+            IncDecTarget::H => {
+                $self.registers.h = $self.$operation($self.registers.h);
+
+                1
+            }
+            IncDecTarget::L => {
+                $self.registers.l = $self.$operation($self.registers.l);
+
+                1
+            }
+            IncDecTarget::Hli => {
+                let address = $self.registers.get_hl();
+                let value = $self.read(address);
+                let new_value = $self.$operation(value);
+                $self.write(address, new_value);
+
+                3
+            }
+            IncDecTarget::BC => {
+                let value = $self.registers.get_bc().$word_operation(1);
+                $self.registers.set_bc(value);
+
+                2
+            }
+            IncDecTarget::DE => {
+                let value = $self.registers.get_de().$word_operation(1);
+                $self.registers.set_de(value);
+
+                2
+            }
+            IncDecTarget::HL => {
+                let value = $self.registers.get_hl().$word_operation(1);
+                $self.registers.set_hl(value);
+
+                2
+            }
+            IncDecTarget::SP => {
+                $self.sp = $self.sp.$word_operation(1);
+
+                2
+            }
+        };
 
-    fn write_byte(&mut self, address: u16, byte: u8) {
-        self.memory[address as usize] = byte;
+        ($self.pc.wrapping_add(1), cycles)
+    }};
+}
+
+/// An error that keeps the CPU from executing any further.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuError {
+    /// The opcode at `address` doesn't exist on the SM83.
+    IllegalOpcode {
+        address: u16,
+        opcode: u8,
+        is_prefixed: bool,
+    },
+    /// The CPU locked up after running into the illegal opcode at `address`.
+    LockedUp { address: u16 },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IllegalOpcode {
+                address,
+                opcode,
+                is_prefixed,
+            } => write!(
+                f,
+                "Illegal instruction 0x{}{opcode:02X} found at 0x{address:04X}!",
+                if *is_prefixed { "CB" } else { "" },
+            ),
+            Self::LockedUp { address } => {
+                write!(f, "CPU locked up by the instruction at 0x{address:04X}!")
+            }
+        }
     }
 }
 
-macro_rules! add_instruction {
-    ($self:expr, $register:ident) => {{
-        let value = $self.registers.$register;
-        let new_value = $self.add(value);
-        $self.registers.a = new_value;
+impl std::error::Error for CpuError {}
 
-        $self.pc.wrapping_add(1)
-    }};
+/// What the CPU does when it fetches an illegal opcode.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalOpcodeBehavior {
+    /// Report [`CpuError::IllegalOpcode`], then [`CpuError::LockedUp`] on every later step.
+    #[default]
+    Error,
+    /// Hang like real hardware does, without servicing interrupts, while stepping keeps
+    /// succeeding.
+    Lock,
 }
 
 #[derive(Debug)]
-struct Cpu {
+pub struct Cpu {
     registers: Registers,
     pc: u16,
+    sp: u16,
     bus: MemoryBus,
+    is_halted: bool,
+    /// Set when `HALT` is executed with `IME` cleared and an interrupt already pending, in which
+    /// case the CPU fails to increment `PC` after the next opcode fetch.
+    halt_bug: bool,
+    /// The interrupt master enable flag.
+    ime: bool,
+    /// Set by `EI`, which only enables interrupts after the following instruction.
+    ime_scheduled: bool,
+    illegal_opcode_behavior: IllegalOpcodeBehavior,
+    /// The address of the illegal opcode that locked up the CPU, if any.
+    locked_at: Option<u16>,
+    /// The M-cycles the bus has been ticked through by the memory accesses of the current step.
+    ticked_m_cycles: u32,
+    /// The T-cycles of normal speed time those M-cycles took.
+    ticked_t_cycles: u32,
 }
 
 impl Cpu {
-    fn step(&mut self) {
-        let mut instruction_byte = self.bus.read_byte(self.pc);
+    /// Creates a CPU that starts executing the boot ROM mapped on `bus`, or, without one, at the
+    /// cartridge's entry point with the machine in the state the boot ROM would have left it.
+    pub fn new(mut bus: MemoryBus) -> Self {
+        let (registers, pc, sp) = if bus.is_boot_rom_mapped() {
+            (Registers::default(), 0x0000, 0x0000)
+        } else {
+            bus.apply_post_boot_state();
+
+            (Registers::post_boot(&bus), 0x0100, 0xFFFE)
+        };
+
+        Self {
+            registers,
+            pc,
+            sp,
+            bus,
+            is_halted: false,
+            halt_bug: false,
+            ime: false,
+            ime_scheduled: false,
+            illegal_opcode_behavior: IllegalOpcodeBehavior::default(),
+            locked_at: None,
+            ticked_m_cycles: 0,
+            ticked_t_cycles: 0,
+        }
+    }
+
+    pub fn set_illegal_opcode_behavior(&mut self, behavior: IllegalOpcodeBehavior) {
+        self.illegal_opcode_behavior = behavior;
+    }
+
+    pub const fn bus(&self) -> &MemoryBus {
+        &self.bus
+    }
+
+    pub fn bus_mut(&mut self) -> &mut MemoryBus {
+        &mut self.bus
+    }
+
+    /// Returns whether an illegal opcode has locked up the CPU.
+    pub const fn is_locked(&self) -> bool {
+        self.locked_at.is_some()
+    }
+
+    /// Executes a single instruction and returns the number of T-cycles it took. T-cycles are
+    /// counted at normal speed, so in CGB double speed mode an instruction takes half as many.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU runs into an illegal opcode, unless it is set to lock up like
+    /// real hardware instead, or if it has already locked up.
+    pub fn step(&mut self) -> Result<u32, CpuError> {
+        self.ticked_m_cycles = 0;
+        self.ticked_t_cycles = 0;
+
+        // Memory accesses tick the bus as they happen, which leaves the internal M-cycles.
+        let m_cycles = self.step_instruction()?;
+        let remaining = m_cycles.saturating_sub(self.ticked_m_cycles) + self.bus.take_stall();
+
+        Ok(self.ticked_t_cycles + self.bus.tick(remaining))
+    }
+
+    /// Runs the rest of the machine through the M-cycle of a memory access.
+    fn tick(&mut self) {
+        self.ticked_m_cycles += 1;
+        self.ticked_t_cycles += self.bus.tick(1);
+    }
+
+    /// Reads `address`, taking an M-cycle.
+    fn read(&mut self, address: u16) -> u8 {
+        self.tick();
+
+        self.bus.read_byte(address)
+    }
+
+    /// Writes `byte` to `address`, taking an M-cycle.
+    fn write(&mut self, address: u16, byte: u8) {
+        self.tick();
+        self.bus.write_byte(address, byte);
+    }
+
+    /// Executes a single instruction, or dispatches an interrupt, and returns the number of
+    /// M-cycles it took.
+    fn step_instruction(&mut self) -> Result<u32, CpuError> {
+        if let Some(address) = self.locked_at {
+            return match self.illegal_opcode_behavior {
+                IllegalOpcodeBehavior::Error => Err(CpuError::LockedUp { address }),
+                IllegalOpcodeBehavior::Lock => Ok(1),
+            };
+        }
+
+        if let Some(m_cycles) = self.handle_interrupts() {
+            return Ok(u32::from(m_cycles));
+        }
+
+        if self.is_halted {
+            return Ok(1);
+        }
+
+        if self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
+        }
+
+        let address = self.pc;
+        let mut instruction_byte = self.read(self.pc);
+
+        if self.halt_bug {
+            // Executing from one byte earlier makes the opcode double as its own first operand
+            // and leaves the next instruction one byte short, just like the skipped increment.
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
+        }
 
         let is_prefixed = instruction_byte == 0xCB;
         if is_prefixed {
-            instruction_byte = self.bus.read_byte(self.pc + 1);
+            instruction_byte = self.read(self.pc.wrapping_add(1));
         }
 
-        let next_pc = Instruction::from_byte(instruction_byte, is_prefixed).map_or_else(
-            || {
-                let description = format!(
-                    "0x{}{instruction_byte:X}",
-                    if is_prefixed { "CB" } else { "" },
-                );
+        let Some(instruction) = Instruction::from_byte(instruction_byte, is_prefixed) else {
+            self.locked_at = Some(address);
 
-                panic!("Unknown instruction found! ({description})")
-            },
-            |instruction| self.execute(instruction),
-        );
+            return match self.illegal_opcode_behavior {
+                IllegalOpcodeBehavior::Error => Err(CpuError::IllegalOpcode {
+                    address,
+                    opcode: instruction_byte,
+                    is_prefixed,
+                }),
+                IllegalOpcodeBehavior::Lock => Ok(1),
+            };
+        };
 
+        let (next_pc, m_cycles) = self.execute(instruction);
         self.pc = next_pc;
+
+        Ok(u32::from(m_cycles))
+    }
+
+    /// Wakes the CPU from `HALT` if an interrupt is pending and, when `IME` is set, dispatches the
+    /// highest priority one. Returns the M-cycles spent dispatching, if anything was dispatched.
+    fn handle_interrupts(&mut self) -> Option<u8> {
+        let pending = self.bus.pending_interrupts();
+        let interrupt = Interrupt::highest_priority(pending)?;
+
+        let was_halted = self.is_halted;
+        self.is_halted = false;
+        self.bus.set_cpu_halted(false);
+
+        if !self.ime {
+            return None;
+        }
+
+        self.ime = false;
+        self.bus.acknowledge_interrupt(interrupt);
+        self.push(self.pc);
+        self.pc = interrupt.vector();
+
+        // Waking up from `HALT` takes one extra M-cycle on top of the dispatch itself.
+        Some(if was_halted { 6 } else { 5 })
+    }
+
+    /// Runs whole instructions until at least `cycles` T-cycles have passed, returning the number
+    /// of T-cycles actually run.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error [`Self::step`] runs into.
+    pub fn run_for(&mut self, cycles: u32) -> Result<u32, CpuError> {
+        let mut elapsed = 0;
+        while elapsed < cycles {
+            elapsed += self.step()?;
+        }
+
+        Ok(elapsed)
+    }
+
+    /// Runs whole instructions until the PPU finishes a frame, returning the number of T-cycles
+    /// run. With the LCD off no frame ever finishes, so this stops after a frame's worth of
+    /// T-cycles instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error [`Self::step`] runs into.
+    pub fn run_frame(&mut self) -> Result<u32, CpuError> {
+        let frame_count = self.bus.ppu().frame_count();
+        let mut elapsed = 0;
+        while self.bus.ppu().frame_count() == frame_count && elapsed < DOTS_PER_FRAME {
+            elapsed += self.step()?;
+        }
+
+        Ok(elapsed)
+    }
+
+    fn read_next_byte(&mut self) -> u8 {
+        self.read(self.pc.wrapping_add(1))
     }
 
-    fn read_next_byte(&self) -> u8 {
-        unimplemented!()
+    fn read_next_word(&mut self) -> u16 {
+        let least_significant_byte = u16::from(self.read(self.pc.wrapping_add(1)));
+        let most_significant_byte = u16::from(self.read(self.pc.wrapping_add(2)));
+
+        (most_significant_byte << 8) | least_significant_byte
     }
 
-    fn execute(&mut self, instruction: Instruction) -> u16 {
+    /// Executes `instruction`, returning the address of the next instruction and the number of
+    /// M-cycles taken.
+    fn execute(&mut self, instruction: Instruction) -> (u16, u8) {
         match instruction {
-            Instruction::Add(target) => match target {
-                ArithmeticTarget::A => add_instruction!(self, a),
-                ArithmeticTarget::B => add_instruction!(self, b),
-                ArithmeticTarget::C => add_instruction!(self, c),
-                ArithmeticTarget::D => add_instruction!(self, d),
-                ArithmeticTarget::E => add_instruction!(self, e),
-                ArithmeticTarget::H => add_instruction!(self, h),
-                ArithmeticTarget::L => add_instruction!(self, l),
-            },
-            Instruction::Jp(jump_test) => {
-                let should_jump = match jump_test {
-                    JumpTest::NotZero => !self.registers.f.zero,
-                    JumpTest::NotCarry => !self.registers.f.carry,
-                    JumpTest::Zero => self.registers.f.zero,
-                    JumpTest::Carry => self.registers.f.carry,
-                    JumpTest::Always => true,
+            Instruction::Nop => (self.pc.wrapping_add(1), 1),
+            // Outside of a CGB speed switch, the low-power mode STOP enters isn't emulated, so it
+            // only skips its padding byte.
+            Instruction::Stop => {
+                self.bus.switch_speed();
+
+                (self.pc.wrapping_add(2), 1)
+            }
+            Instruction::Halt => {
+                if !self.ime && self.bus.pending_interrupts() != 0 {
+                    self.halt_bug = true;
+                } else {
+                    self.is_halted = true;
+                    self.bus.set_cpu_halted(true);
+                }
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Di => {
+                self.ime = false;
+                self.ime_scheduled = false;
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Ei => {
+                self.ime_scheduled = true;
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Add(target) => arithmetic_instruction!(self, target, add),
+            Instruction::Adc(target) => arithmetic_instruction!(self, target, add_with_carry),
+            Instruction::Sub(target) => arithmetic_instruction!(self, target, sub),
+            Instruction::Sbc(target) => arithmetic_instruction!(self, target, sub_with_carry),
+            Instruction::And(target) => arithmetic_instruction!(self, target, and),
+            Instruction::Xor(target) => arithmetic_instruction!(self, target, xor),
+            Instruction::Or(target) => arithmetic_instruction!(self, target, or),
+            Instruction::Cp(target) => arithmetic_instruction!(self, target, compare),
+            Instruction::AddHl(target) => {
+                let value = match target {
+                    AddHlTarget::BC => self.registers.get_bc(),
+                    AddHlTarget::DE => self.registers.get_de(),
+                    AddHlTarget::HL => self.registers.get_hl(),
+                    AddHlTarget::SP => self.sp,
                 };
+                let new_value = self.add_hl(value);
+                self.registers.set_hl(new_value);
+
+                (self.pc.wrapping_add(1), 2)
+            }
+            Instruction::AddSp => {
+                self.sp = self.offset_sp();
+
+                (self.pc.wrapping_add(2), 4)
+            }
+            Instruction::Inc(target) => inc_dec_instruction!(self, target, inc, wrapping_add),
+            Instruction::Dec(target) => inc_dec_instruction!(self, target, dec, wrapping_sub),
+            Instruction::Rlca => {
+                self.registers.a = self.rotate_left(self.registers.a, false);
+                self.registers.f.zero = false;
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Rrca => {
+                self.registers.a = self.rotate_right(self.registers.a, false);
+                self.registers.f.zero = false;
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Rla => {
+                self.registers.a = self.rotate_left(self.registers.a, true);
+                self.registers.f.zero = false;
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Rra => {
+                self.registers.a = self.rotate_right(self.registers.a, true);
+                self.registers.f.zero = false;
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Daa => {
+                self.decimal_adjust();
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Cpl => {
+                self.registers.a = !self.registers.a;
+                self.registers.f.subtract = true;
+                self.registers.f.half_carry = true;
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Scf => {
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = true;
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Ccf => {
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = !self.registers.f.carry;
+
+                (self.pc.wrapping_add(1), 1)
+            }
+            Instruction::Jp(jump_test) => {
+                let should_jump = self.should_jump(&jump_test);
 
                 self.jump(should_jump)
             }
-            Instruction::Ld(load_type) => match load_type {
-                LoadType::Byte(target, source) => {
-                    let source_value = match source {
-                        LoadByteSource::A => self.registers.a,
-                        LoadByteSource::D8 => self.read_next_byte(),
-                        LoadByteSource::Hli => self.bus.read_byte(self.registers.get_hl()),
-                        _ => panic!("TODO: Implement other sources!"),
-                    };
+            Instruction::JpHl => (self.registers.get_hl(), 1),
+            Instruction::Jr(jump_test) => {
+                let should_jump = self.should_jump(&jump_test);
 
-                    match target {
-                        LoadByteTarget::A => self.registers.a = source_value,
-                        LoadByteTarget::Hli => {
-                            self.bus.write_byte(self.registers.get_hl(), source_value)
-                        }
-                        _ => panic!("TODO: Implement other targets!"),
-                    };
+                self.jump_relative(should_jump)
+            }
+            Instruction::Call(jump_test) => {
+                let should_jump = self.should_jump(&jump_test);
 
-                    match source {
-                        LoadByteSource::D8 => self.pc.wrapping_add(2),
-                        _ => self.pc.wrapping_add(1),
-                    }
-                }
-            },
-            _ => self.pc,
-        }
-    }
+                self.call(should_jump)
+            }
+            Instruction::Ret(JumpTest::Always) => {
+                let (next_pc, _) = self.return_(true);
 
-    fn add(&mut self, value: u8) -> u8 {
-        let (new_value, did_overflow) = self.registers.a.overflowing_add(value);
+                (next_pc, 4)
+            }
+            Instruction::Ret(jump_test) => {
+                let should_jump = self.should_jump(&jump_test);
 
-        self.registers.f.zero = new_value == 0;
-        self.registers.f.subtract = false;
-        self.registers.f.carry = did_overflow;
-        self.registers.f.half_carry = (self.registers.a & 0xF) + (value & 0xF) > 0xF;
+                self.return_(should_jump)
+            }
+            // Unlike `EI`, `RETI` enables interrupts immediately.
+            Instruction::Reti => {
+                self.ime = true;
 
-        new_value
-    }
+                let (next_pc, _) = self.return_(true);
 
-    fn jump(&self, should_jump: bool) -> u16 {
-        if should_jump {
-            let least_significant_byte = u16::from(self.bus.read_byte(self.pc + 1));
-            let most_significant_byte = u16::from(self.bus.read_byte(self.pc + 2));
+                (next_pc, 4)
+            }
+            Instruction::Rst(vector) => {
+                self.push(self.pc.wrapping_add(1));
 
-            (most_significant_byte << 8) | least_significant_byte
-        } else {
-            self.pc.wrapping_add(3)
-        }
+                (u16::from(vector), 4)
+            }
+            Instruction::Push(target) => {
+                let value = match target {
+                    StackTarget::AF => self.registers.get_af(),
+                    StackTarget::BC => self.registers.get_bc(),
+                    StackTarget::DE => self.registers.get_de(),
+                    StackTarget::HL => self.registers.get_hl(),
+                };
+                self.push(value);
+
+                (self.pc.wrapping_add(1), 4)
+            }
+            Instruction::Pop(target) => {
+                let value = self.pop();
+                match target {
+                    StackTarget::AF => self.registers.set_af(value),
+                    StackTarget::BC => self.registers.set_bc(value),
+                    StackTarget::DE => self.registers.set_de(value),
+                    StackTarget::HL => self.registers.set_hl(value),
+                };
+
+                (self.pc.wrapping_add(1), 3)
+            }
+            Instruction::Rlc(target) => {
+                self.modify_prefix_target(&target, |cpu, value| cpu.rotate_left(value, false))
+            }
+            Instruction::Rrc(target) => {
+                self.modify_prefix_target(&target, |cpu, value| cpu.rotate_right(value, false))
+            }
+            Instruction::Rl(target) => {
+                self.modify_prefix_target(&target, |cpu, value| cpu.rotate_left(value, true))
+            }
+            Instruction::Rr(target) => {
+                self.modify_prefix_target(&target, |cpu, value| cpu.rotate_right(value, true))
+            }
+            Instruction::Sla(target) => self.modify_prefix_target(&target, Self::shift_left),
+            Instruction::Sra(target) => {
+                self.modify_prefix_target(&target, Self::shift_right_arithmetic)
+            }
+            Instruction::Swap(target) => self.modify_prefix_target(&target, Self::swap),
+            Instruction::Srl(target) => {
+                self.modify_prefix_target(&target, Self::shift_right_logical)
+            }
+            Instruction::Bit(bit, target) => {
+                let value = self.read_prefix_target(&target);
+
+                self.registers.f.zero = value & (1 << bit) == 0;
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = true;
+
+                match target {
+                    PrefixTarget::Hli => (self.pc.wrapping_add(2), 3),
+                    _ => (self.pc.wrapping_add(2), 2),
+                }
+            }
+            Instruction::Res(bit, target) => {
+                let value = self.read_prefix_target(&target);
+                self.write_prefix_target(&target, value & !(1 << bit));
+
+                Self::prefix_result(self.pc, &target)
+            }
+            Instruction::Set(bit, target) => {
+                let value = self.read_prefix_target(&target);
+                self.write_prefix_target(&target, value | (1 << bit));
+
+                Self::prefix_result(self.pc, &target)
+            }
+            Instruction::Ld(load_type) => self.load(load_type),
+        }
+    }
+
+    /// Applies `operation` to a prefixed instruction's operand and stores the result back.
+    fn modify_prefix_target(
+        &mut self,
+        target: &PrefixTarget,
+        operation: fn(&mut Self, u8) -> u8,
+    ) -> (u16, u8) {
+        let value = self.read_prefix_target(target);
+        let new_value = operation(self, value);
+        self.write_prefix_target(target, new_value);
+
+        Self::prefix_result(self.pc, target)
+    }
+
+    /// Returns the next address and M-cycle cost of a prefixed instruction that writes back to
+    /// its operand.
+    const fn prefix_result(pc: u16, target: &PrefixTarget) -> (u16, u8) {
+        match target {
+            PrefixTarget::Hli => (pc.wrapping_add(2), 4),
+            _ => (pc.wrapping_add(2), 2),
+        }
+    }
+
+    fn read_prefix_target(&mut self, target: &PrefixTarget) -> u8 {
+        match target {
+            PrefixTarget::A => self.registers.a,
+            PrefixTarget::B => self.registers.b,
+            PrefixTarget::C => self.registers.c,
+            PrefixTarget::D => self.registers.d,
+            PrefixTarget::E => self.registers.e,
+            PrefixTarget::H => self.registers.h,
+            PrefixTarget::L => self.registers.l,
+            PrefixTarget::Hli => self.read(self.registers.get_hl()),
+        }
+    }
+
+    fn write_prefix_target(&mut self, target: &PrefixTarget, value: u8) {
+        match target {
+            PrefixTarget::A => self.registers.a = value,
+            PrefixTarget::B => self.registers.b = value,
+            PrefixTarget::C => self.registers.c = value,
+            PrefixTarget::D => self.registers.d = value,
+            PrefixTarget::E => self.registers.e = value,
+            PrefixTarget::H => self.registers.h = value,
+            PrefixTarget::L => self.registers.l = value,
+            PrefixTarget::Hli => self.write(self.registers.get_hl(), value),
+        }
+    }
+
+    fn load(&mut self, load_type: LoadType) -> (u16, u8) {
+        match load_type {
+            LoadType::Byte(target, source) => {
+                let source_value = match source {
+                    LoadByteSource::A => self.registers.a,
+                    LoadByteSource::B => self.registers.b,
+                    LoadByteSource::C => self.registers.c,
+                    LoadByteSource::D => self.registers.d,
+                    LoadByteSource::E => self.registers.e,
+                    LoadByteSource::H => self.registers.h,
+                    LoadByteSource::L => self.registers.l,
+                    LoadByteSource::D8 => self.read_next_byte(),
+                    LoadByteSource::Hli => self.read(self.registers.get_hl()),
+                };
+
+                match target {
+                    LoadByteTarget::A => self.registers.a = source_value,
+                    LoadByteTarget::B => self.registers.b = source_value,
+                    LoadByteTarget::C => self.registers.c = source_value,
+                    LoadByteTarget::D => self.registers.d = source_value,
+                    LoadByteTarget::E => self.registers.e = source_value,
+                    LoadByteTarget::H => self.registers.h = source_value,
+                    LoadByteTarget::L => self.registers.l = source_value,
+                    LoadByteTarget::Hli => {
+                        self.write(self.registers.get_hl(), source_value);
+                    }
+                };
+
+                match (target, source) {
+                    (LoadByteTarget::Hli, LoadByteSource::D8) => (self.pc.wrapping_add(2), 3),
+                    (_, LoadByteSource::D8) => (self.pc.wrapping_add(2), 2),
+                    (LoadByteTarget::Hli, _) | (_, LoadByteSource::Hli) => {
+                        (self.pc.wrapping_add(1), 2)
+                    }
+                    _ => (self.pc.wrapping_add(1), 1),
+                }
+            }
+            LoadType::Word(target) => {
+                let word = self.read_next_word();
+                match target {
+                    LoadWordTarget::BC => self.registers.set_bc(word),
+                    LoadWordTarget::DE => self.registers.set_de(word),
+                    LoadWordTarget::HL => self.registers.set_hl(word),
+                    LoadWordTarget::SP => self.sp = word,
+                };
+
+                (self.pc.wrapping_add(3), 3)
+            }
+            LoadType::AFromIndirect(indirect) => {
+                let address = self.indirect_address(&indirect);
+                self.registers.a = self.read(address);
+
+                match indirect {
+                    Indirect::Word => (self.pc.wrapping_add(3), 4),
+                    _ => (self.pc.wrapping_add(1), 2),
+                }
+            }
+            LoadType::IndirectFromA(indirect) => {
+                let address = self.indirect_address(&indirect);
+                self.write(address, self.registers.a);
+
+                match indirect {
+                    Indirect::Word => (self.pc.wrapping_add(3), 4),
+                    _ => (self.pc.wrapping_add(1), 2),
+                }
+            }
+            LoadType::AFromByteAddress => {
+                let address = 0xFF00 | u16::from(self.read_next_byte());
+                self.registers.a = self.read(address);
+
+                (self.pc.wrapping_add(2), 3)
+            }
+            LoadType::ByteAddressFromA => {
+                let address = 0xFF00 | u16::from(self.read_next_byte());
+                self.write(address, self.registers.a);
+
+                (self.pc.wrapping_add(2), 3)
+            }
+            LoadType::IndirectFromSp => {
+                let address = self.read_next_word();
+                self.write(address, (self.sp & 0xFF) as u8);
+                self.write(address.wrapping_add(1), ((self.sp & 0xFF00) >> 8) as u8);
+
+                (self.pc.wrapping_add(3), 5)
+            }
+            LoadType::SpFromHl => {
+                self.sp = self.registers.get_hl();
+
+                (self.pc.wrapping_add(1), 2)
+            }
+            LoadType::HlFromSpOffset => {
+                let value = self.offset_sp();
+                self.registers.set_hl(value);
+
+                (self.pc.wrapping_add(2), 3)
+            }
+        }
+    }
+
+    fn read_arithmetic_target(&mut self, target: &ArithmeticTarget) -> u8 {
+        match target {
+            ArithmeticTarget::A => self.registers.a,
+            ArithmeticTarget::B => self.registers.b,
+            ArithmeticTarget::C => self.registers.c,
+            ArithmeticTarget::D => self.registers.d,
+            ArithmeticTarget::E => self.registers.e,
+            ArithmeticTarget::H => self.registers.h,
+            ArithmeticTarget::L => self.registers.l,
+            ArithmeticTarget::Hli => self.read(self.registers.get_hl()),
+            ArithmeticTarget::D8 => self.read_next_byte(),
+        }
+    }
+
+    fn indirect_address(&mut self, indirect: &Indirect) -> u16 {
+        match indirect {
+            Indirect::BC => self.registers.get_bc(),
+            Indirect::DE => self.registers.get_de(),
+            Indirect::HLIncrement => {
+                let address = self.registers.get_hl();
+                self.registers.set_hl(address.wrapping_add(1));
+
+                address
+            }
+            Indirect::HLDecrement => {
+                let address = self.registers.get_hl();
+                self.registers.set_hl(address.wrapping_sub(1));
+
+                address
+            }
+            Indirect::Word => self.read_next_word(),
+            Indirect::HighC => 0xFF00 | u16::from(self.registers.c),
+        }
+    }
+
+    const fn should_jump(&self, jump_test: &JumpTest) -> bool {
+        match jump_test {
+            JumpTest::NotZero => !self.registers.f.zero,
+            JumpTest::NotCarry => !self.registers.f.carry,
+            JumpTest::Zero => self.registers.f.zero,
+            JumpTest::Carry => self.registers.f.carry,
+            JumpTest::Always => true,
+        }
+    }
+
+    fn add(&mut self, value: u8) -> u8 {
+        let (new_value, did_overflow) = self.registers.a.overflowing_add(value);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = did_overflow;
+        self.registers.f.half_carry = (self.registers.a & 0xF) + (value & 0xF) > 0xF;
+
+        new_value
+    }
+
+    fn add_with_carry(&mut self, value: u8) -> u8 {
+        let carry = u8::from(self.registers.f.carry);
+        let new_value = self.registers.a.wrapping_add(value).wrapping_add(carry);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry =
+            u16::from(self.registers.a) + u16::from(value) + u16::from(carry) > 0xFF;
+        self.registers.f.half_carry = (self.registers.a & 0xF) + (value & 0xF) + carry > 0xF;
+
+        new_value
+    }
+
+    fn sub(&mut self, value: u8) -> u8 {
+        let (new_value, did_overflow) = self.registers.a.overflowing_sub(value);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.carry = did_overflow;
+        self.registers.f.half_carry = (self.registers.a & 0xF) < (value & 0xF);
+
+        new_value
+    }
+
+    fn sub_with_carry(&mut self, value: u8) -> u8 {
+        let carry = u8::from(self.registers.f.carry);
+        let new_value = self.registers.a.wrapping_sub(value).wrapping_sub(carry);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.carry = u16::from(self.registers.a) < u16::from(value) + u16::from(carry);
+        self.registers.f.half_carry = (self.registers.a & 0xF) < (value & 0xF) + carry;
+
+        new_value
+    }
+
+    fn and(&mut self, value: u8) -> u8 {
+        let new_value = self.registers.a & value;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = false;
+        self.registers.f.half_carry = true;
+
+        new_value
+    }
+
+    fn xor(&mut self, value: u8) -> u8 {
+        let new_value = self.registers.a ^ value;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = false;
+        self.registers.f.half_carry = false;
+
+        new_value
+    }
+
+    fn or(&mut self, value: u8) -> u8 {
+        let new_value = self.registers.a | value;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = false;
+        self.registers.f.half_carry = false;
+
+        new_value
+    }
+
+    /// Sets the flags like [`Self::sub`] but leaves `A` untouched.
+    fn compare(&mut self, value: u8) -> u8 {
+        self.sub(value);
+
+        self.registers.a
+    }
+
+    fn inc(&mut self, value: u8) -> u8 {
+        let new_value = value.wrapping_add(1);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = value & 0xF == 0xF;
+
+        new_value
+    }
+
+    fn dec(&mut self, value: u8) -> u8 {
+        let new_value = value.wrapping_sub(1);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.half_carry = value & 0xF == 0;
+
+        new_value
+    }
+
+    fn add_hl(&mut self, value: u16) -> u16 {
+        let hl = self.registers.get_hl();
+        let (new_value, did_overflow) = hl.overflowing_add(value);
+
+        self.registers.f.subtract = false;
+        self.registers.f.carry = did_overflow;
+        self.registers.f.half_carry = (hl & 0xFFF) + (value & 0xFFF) > 0xFFF;
+
+        new_value
+    }
+
+    /// Adds the signed immediate byte to `SP`, setting the carries from the unsigned low byte.
+    fn offset_sp(&mut self) -> u16 {
+        let value = self.read_next_byte();
+        let new_value = self.sp.wrapping_add_signed(i16::from(value as i8));
+
+        self.registers.f.zero = false;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = (self.sp & 0xFF) + u16::from(value) > 0xFF;
+        self.registers.f.half_carry = (self.sp & 0xF) + u16::from(value & 0xF) > 0xF;
+
+        new_value
+    }
+
+    fn rotate_left(&mut self, value: u8, through_carry: bool) -> u8 {
+        let carry_in = if through_carry {
+            u8::from(self.registers.f.carry)
+        } else {
+            value >> 7
+        };
+        let new_value = (value << 1) | carry_in;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = value & 0x80 != 0;
+        self.registers.f.half_carry = false;
+
+        new_value
+    }
+
+    fn rotate_right(&mut self, value: u8, through_carry: bool) -> u8 {
+        let carry_in = if through_carry {
+            u8::from(self.registers.f.carry)
+        } else {
+            value & 0b1
+        };
+        let new_value = (value >> 1) | (carry_in << 7);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = value & 0b1 != 0;
+        self.registers.f.half_carry = false;
+
+        new_value
+    }
+
+    fn shift_left(&mut self, value: u8) -> u8 {
+        let new_value = value << 1;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = value & 0x80 != 0;
+        self.registers.f.half_carry = false;
+
+        new_value
+    }
+
+    /// Shifts right while keeping the sign bit in place.
+    fn shift_right_arithmetic(&mut self, value: u8) -> u8 {
+        let new_value = (value >> 1) | (value & 0x80);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = value & 0b1 != 0;
+        self.registers.f.half_carry = false;
+
+        new_value
+    }
+
+    fn shift_right_logical(&mut self, value: u8) -> u8 {
+        let new_value = value >> 1;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = value & 0b1 != 0;
+        self.registers.f.half_carry = false;
+
+        new_value
+    }
+
+    fn swap(&mut self, value: u8) -> u8 {
+        let new_value = value.rotate_left(4);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = false;
+        self.registers.f.half_carry = false;
+
+        new_value
+    }
+
+    /// Corrects `A` to packed BCD after an addition or subtraction of two BCD numbers.
+    fn decimal_adjust(&mut self) {
+        let mut correction = 0;
+        let mut carry = self.registers.f.carry;
+
+        if self.registers.f.subtract {
+            if self.registers.f.half_carry {
+                correction |= 0x06;
+            }
+            if carry {
+                correction |= 0x60;
+            }
+
+            self.registers.a = self.registers.a.wrapping_sub(correction);
+        } else {
+            if self.registers.f.half_carry || self.registers.a & 0xF > 0x9 {
+                correction |= 0x06;
+            }
+            if carry || self.registers.a > 0x99 {
+                correction |= 0x60;
+                carry = true;
+            }
+
+            self.registers.a = self.registers.a.wrapping_add(correction);
+        }
+
+        self.registers.f.zero = self.registers.a == 0;
+        self.registers.f.carry = carry;
+        self.registers.f.half_carry = false;
+    }
+
+    fn jump(&mut self, should_jump: bool) -> (u16, u8) {
+        if should_jump {
+            (self.read_next_word(), 4)
+        } else {
+            (self.pc.wrapping_add(3), 3)
+        }
+    }
+
+    fn jump_relative(&mut self, should_jump: bool) -> (u16, u8) {
+        let next_pc = self.pc.wrapping_add(2);
+
+        if should_jump {
+            let offset = self.read_next_byte() as i8;
+
+            (next_pc.wrapping_add_signed(i16::from(offset)), 3)
+        } else {
+            (next_pc, 2)
+        }
+    }
+
+    fn call(&mut self, should_jump: bool) -> (u16, u8) {
+        let next_pc = self.pc.wrapping_add(3);
+
+        if should_jump {
+            self.push(next_pc);
+
+            (self.read_next_word(), 6)
+        } else {
+            (next_pc, 3)
+        }
+    }
+
+    fn return_(&mut self, should_jump: bool) -> (u16, u8) {
+        if should_jump {
+            (self.pop(), 5)
+        } else {
+            (self.pc.wrapping_add(1), 2)
+        }
+    }
+
+    /// Pushes `value` onto the stack, which grows downwards, most significant byte first.
+    fn push(&mut self, value: u16) {
+        self.sp = self.sp.wrapping_sub(1);
+        self.write(self.sp, ((value & 0xFF00) >> 8) as u8);
+
+        self.sp = self.sp.wrapping_sub(1);
+        self.write(self.sp, (value & 0xFF) as u8);
+    }
+
+    /// Pops a value pushed by [`Self::push`] off the stack.
+    fn pop(&mut self) -> u16 {
+        let least_significant_byte = u16::from(self.read(self.sp));
+        self.sp = self.sp.wrapping_add(1);
+
+        let most_significant_byte = u16::from(self.read(self.sp));
+        self.sp = self.sp.wrapping_add(1);
+
+        (most_significant_byte << 8) | least_significant_byte
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::{boot::BootRom, mbc::RomOnly};
+
+    const PROGRAM_START: usize = 0x0100;
+
+    /// Returns a CPU about to run `program` from the cartridge entry point of an otherwise empty
+    /// 32 KiB ROM.
+    fn cpu_with_program(program: &[u8]) -> Cpu {
+        let mut rom = vec![0; 0x8000];
+        rom[PROGRAM_START..PROGRAM_START + program.len()].copy_from_slice(program);
+
+        Cpu::new(MemoryBus::new(
+            Box::new(RomOnly::new(rom, Vec::new())),
+            Model::Dmg,
+        ))
+    }
+
+    /// Steps `cpu` through `count` instructions, none of which may fail.
+    fn step_times(cpu: &mut Cpu, count: usize) {
+        for _ in 0..count {
+            cpu.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn every_illegal_opcode_is_rejected() {
+        for opcode in [
+            0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+        ] {
+            let mut cpu = cpu_with_program(&[opcode]);
+
+            assert_eq!(
+                cpu.step(),
+                Err(CpuError::IllegalOpcode {
+                    address: 0x0100,
+                    opcode,
+                    is_prefixed: false,
+                })
+            );
+            assert_eq!(cpu.step(), Err(CpuError::LockedUp { address: 0x0100 }));
+        }
+    }
+
+    #[test]
+    fn lock_behavior_hangs_without_interrupts() {
+        let mut cpu = cpu_with_program(&[0xD3]);
+        cpu.set_illegal_opcode_behavior(IllegalOpcodeBehavior::Lock);
+        cpu.ime = true;
+        cpu.bus.write_byte(IE_ADDRESS, Interrupt::Timer.mask());
+        assert_eq!(cpu.step(), Ok(4));
+        assert!(cpu.is_locked());
+
+        cpu.bus.write_byte(IF_ADDRESS, Interrupt::Timer.mask());
+        for _ in 0..3 {
+            assert_eq!(cpu.step(), Ok(4));
+            assert!(cpu.is_locked());
+            assert_eq!(cpu.pc, 0x0100, "the interrupt is never dispatched");
+        }
+    }
+
+    #[test]
+    fn every_prefixed_opcode_decodes() {
+        for opcode in 0..=u8::MAX {
+            assert!(Instruction::from_byte(opcode, true).is_some());
+        }
+    }
+
+    #[test]
+    fn prefixed_instructions_execute() {
+        // LD A, 0xF1; SWAP A; BIT 7, A; SET 7, A
+        let mut cpu = cpu_with_program(&[0x3E, 0xF1, 0xCB, 0x37, 0xCB, 0x7F, 0xCB, 0xFF]);
+
+        step_times(&mut cpu, 2);
+        assert_eq!(cpu.registers.a, 0x1F);
+        assert_eq!(cpu.pc, 0x0104);
+
+        step_times(&mut cpu, 1);
+        assert!(cpu.registers.f.zero);
+        assert!(cpu.registers.f.half_carry);
+
+        step_times(&mut cpu, 1);
+        assert_eq!(cpu.registers.a, 0x9F);
+        assert_eq!(cpu.pc, 0x0108);
+    }
+
+    /// Runs `LD A, a; <opcode> b; DAA` and returns the CPU afterwards.
+    fn decimal_adjusted(a: u8, opcode: u8, b: u8) -> Cpu {
+        let mut cpu = cpu_with_program(&[0x3E, a, opcode, b, 0x27]);
+        step_times(&mut cpu, 3);
+
+        cpu
+    }
+
+    #[test]
+    fn daa_after_add() {
+        const ADD: u8 = 0xC6;
+
+        let cpu = decimal_adjusted(0x15, ADD, 0x27);
+        assert_eq!(cpu.registers.a, 0x42);
+        assert!(!cpu.registers.f.carry);
+
+        // 0x19 + 0x28 = 0x41 only carries out of the low nibble.
+        let cpu = decimal_adjusted(0x19, ADD, 0x28);
+        assert_eq!(cpu.registers.a, 0x47);
+        assert!(!cpu.registers.f.half_carry);
+
+        let cpu = decimal_adjusted(0x99, ADD, 0x01);
+        assert_eq!(cpu.registers.a, 0x00);
+        assert!(cpu.registers.f.zero);
+        assert!(cpu.registers.f.carry);
+    }
+
+    #[test]
+    fn daa_after_sub() {
+        const SUB: u8 = 0xD6;
+
+        // 0x42 - 0x15 = 0x2D borrows from the high nibble.
+        let cpu = decimal_adjusted(0x42, SUB, 0x15);
+        assert_eq!(cpu.registers.a, 0x27);
+        assert!(cpu.registers.f.subtract);
+        assert!(!cpu.registers.f.carry);
+
+        let cpu = decimal_adjusted(0x10, SUB, 0x20);
+        assert_eq!(cpu.registers.a, 0x90);
+        assert!(cpu.registers.f.carry);
+    }
+
+    #[test]
+    fn add_sp_sets_carries_from_low_byte() {
+        // LD SP, 0x00FF; ADD SP, 1
+        let mut cpu = cpu_with_program(&[0x31, 0xFF, 0x00, 0xE8, 0x01]);
+        step_times(&mut cpu, 2);
+
+        assert_eq!(cpu.sp, 0x0100);
+        assert!(!cpu.registers.f.zero);
+        assert!(!cpu.registers.f.subtract);
+        assert!(cpu.registers.f.half_carry);
+        assert!(cpu.registers.f.carry);
+
+        // LD SP, 0x1000; ADD SP, -1 doesn't carry out of the low byte.
+        let mut cpu = cpu_with_program(&[0x31, 0x00, 0x10, 0xE8, 0xFF]);
+        step_times(&mut cpu, 2);
+
+        assert_eq!(cpu.sp, 0x0FFF);
+        assert!(!cpu.registers.f.half_carry);
+        assert!(!cpu.registers.f.carry);
+    }
+
+    #[test]
+    fn ld_hl_sp_offset_never_sets_zero() {
+        // LD SP, 0x0001; LD HL, SP-1
+        let mut cpu = cpu_with_program(&[0x31, 0x01, 0x00, 0xF8, 0xFF]);
+        step_times(&mut cpu, 2);
+
+        assert_eq!(cpu.registers.get_hl(), 0x0000);
+        assert_eq!(cpu.sp, 0x0001);
+        assert!(!cpu.registers.f.zero);
+        assert!(!cpu.registers.f.subtract);
+        assert!(cpu.registers.f.half_carry);
+        assert!(cpu.registers.f.carry);
+    }
+
+    #[test]
+    fn pc_wraps_at_end_of_address_space() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0000] = 0x42;
+        let mut cpu = Cpu::new(MemoryBus::new(
+            Box::new(RomOnly::new(rom, Vec::new())),
+            Model::Dmg,
+        ));
+
+        // LD A, n at 0xFFFF, whose operand is the first byte of the ROM.
+        cpu.bus.write_byte(0xFFFF, 0x3E);
+        cpu.pc = 0xFFFF;
+        step_times(&mut cpu, 1);
+
+        assert_eq!(cpu.registers.a, 0x42);
+        assert_eq!(cpu.pc, 0x0001);
+    }
+
+    /// Runs `XOR A`, which sets the zero flag, followed by `instruction`, and returns the T-cycles
+    /// `instruction` took.
+    fn cycles_with_zero_set(instruction: &[u8]) -> u32 {
+        let mut program = vec![0xAF];
+        program.extend_from_slice(instruction);
+        let mut cpu = cpu_with_program(&program);
+        step_times(&mut cpu, 1);
+
+        cpu.step().unwrap()
+    }
+
+    #[test]
+    fn taken_branches_take_longer() {
+        // JR Z / JR NZ
+        assert_eq!(cycles_with_zero_set(&[0x28, 0x00]), 12);
+        assert_eq!(cycles_with_zero_set(&[0x20, 0x00]), 8);
+        // JP Z / JP NZ
+        assert_eq!(cycles_with_zero_set(&[0xCA, 0x00, 0x02]), 16);
+        assert_eq!(cycles_with_zero_set(&[0xC2, 0x00, 0x02]), 12);
+        // CALL Z / CALL NZ
+        assert_eq!(cycles_with_zero_set(&[0xCC, 0x00, 0x02]), 24);
+        assert_eq!(cycles_with_zero_set(&[0xC4, 0x00, 0x02]), 12);
+        // RET Z / RET NZ
+        assert_eq!(cycles_with_zero_set(&[0xC8]), 20);
+        assert_eq!(cycles_with_zero_set(&[0xC0]), 8);
+        // Unconditional JR, JP, CALL and RET
+        assert_eq!(cycles_with_zero_set(&[0x18, 0x00]), 12);
+        assert_eq!(cycles_with_zero_set(&[0xC3, 0x00, 0x02]), 16);
+        assert_eq!(cycles_with_zero_set(&[0xCD, 0x00, 0x02]), 24);
+        assert_eq!(cycles_with_zero_set(&[0xC9]), 16);
+    }
+
+    #[test]
+    fn bit_on_hl_skips_write_back() {
+        // LD HL, 0xC000, then SWAP (HL) and BIT 0, (HL)
+        let mut cpu = cpu_with_program(&[0x21, 0x00, 0xC0, 0xCB, 0x36, 0xCB, 0x46]);
+        step_times(&mut cpu, 1);
+
+        assert_eq!(cpu.step(), Ok(16));
+        assert_eq!(cpu.step(), Ok(12));
+    }
+
+    #[test]
+    fn run_frame_runs_until_next_frame() {
+        // JR -2
+        let mut cpu = cpu_with_program(&[0x18, 0xFE]);
+        cpu.run_frame().unwrap();
+        let frame_count = cpu.bus.ppu().frame_count();
+
+        let elapsed = cpu.run_frame().unwrap();
+        assert_eq!(cpu.bus.ppu().frame_count(), frame_count + 1);
+        assert!(elapsed.abs_diff(DOTS_PER_FRAME) < 12);
+    }
+
+    #[test]
+    fn run_frame_stops_with_lcd_off() {
+        let mut cpu = cpu_with_program(&[0x18, 0xFE]);
+        cpu.bus.write_byte(0xFF40, 0x00);
+
+        let elapsed = cpu.run_frame().unwrap();
+        assert!((DOTS_PER_FRAME..DOTS_PER_FRAME + 12).contains(&elapsed));
+    }
+
+    const IF_ADDRESS: u16 = 0xFF0F;
+    const IE_ADDRESS: u16 = 0xFFFF;
+
+    /// Returns a CPU about to run `program` with the timer interrupt enabled and already pending.
+    fn cpu_with_timer_interrupt(program: &[u8]) -> Cpu {
+        let mut cpu = cpu_with_program(program);
+        cpu.bus.write_byte(IE_ADDRESS, Interrupt::Timer.mask());
+        cpu.bus.write_byte(IF_ADDRESS, Interrupt::Timer.mask());
+
+        cpu
+    }
+
+    #[test]
+    fn ei_enables_interrupts_after_next_instruction() {
+        // EI; NOP; NOP
+        let mut cpu = cpu_with_timer_interrupt(&[0xFB, 0x00, 0x00]);
+
+        step_times(&mut cpu, 2);
+        assert_eq!(cpu.pc, 0x0102);
+
+        assert_eq!(cpu.step(), Ok(20));
+        assert_eq!(cpu.pc, Interrupt::Timer.vector());
+    }
+
+    #[test]
+    fn halt_bug_repeats_next_byte() {
+        // HALT; INC A
+        let mut cpu = cpu_with_timer_interrupt(&[0x76, 0x3C]);
+        cpu.registers.a = 0;
+
+        step_times(&mut cpu, 3);
+        assert_eq!(cpu.registers.a, 2);
+        assert_eq!(cpu.pc, 0x0102);
+    }
+
+    #[test]
+    fn halt_wakes_without_dispatch_when_ime_is_clear() {
+        // HALT; NOP
+        let mut cpu = cpu_with_program(&[0x76, 0x00]);
+        cpu.bus.write_byte(IE_ADDRESS, Interrupt::Timer.mask());
+        cpu.bus.write_byte(IF_ADDRESS, 0);
+
+        step_times(&mut cpu, 2);
+        assert!(cpu.is_halted);
+        assert_eq!(cpu.pc, 0x0101);
+
+        cpu.bus.write_byte(IF_ADDRESS, Interrupt::Timer.mask());
+        step_times(&mut cpu, 1);
+        assert!(!cpu.is_halted);
+        assert_eq!(cpu.pc, 0x0102);
+        assert_ne!(
+            cpu.bus.read_byte(IF_ADDRESS) & Interrupt::Timer.mask(),
+            0,
+            "the interrupt stays pending"
+        );
+    }
+
+    #[test]
+    fn interrupts_dispatch_in_priority_order() {
+        for (pending, vector) in [
+            (0b1_1111, 0x40),
+            (0b1_1110, 0x48),
+            (0b1_1100, 0x50),
+            (0b1_1000, 0x58),
+            (0b1_0000, 0x60),
+        ] {
+            let mut cpu = cpu_with_program(&[]);
+            cpu.ime = true;
+            cpu.bus.write_byte(IE_ADDRESS, 0b1_1111);
+            cpu.bus.write_byte(IF_ADDRESS, pending);
+
+            step_times(&mut cpu, 1);
+            assert_eq!(cpu.pc, vector);
+            assert!(!cpu.ime);
+            // Only the dispatched interrupt is acknowledged.
+            assert_eq!(
+                cpu.bus.read_byte(IF_ADDRESS) & 0b1_1111,
+                pending & (pending - 1)
+            );
+        }
+    }
+
+    #[test]
+    fn memory_access_sees_timer_at_its_own_m_cycle() {
+        // LD A, (0xFF05), which reads TIMA on its fourth M-cycle.
+        let mut cpu = cpu_with_program(&[0xFA, 0x05, 0xFF]);
+        cpu.bus.write_byte(0xFF07, 0b101);
+        cpu.bus.write_byte(0xFF04, 0);
+        cpu.bus.write_byte(0xFF05, 0);
+
+        assert_eq!(cpu.step(), Ok(16));
+        assert_eq!(cpu.registers.a, 1);
+    }
+
+    #[test]
+    fn cgb_boot_rom_colors_dmg_cartridge() {
+        #[rustfmt::skip]
+        let boot_program = [
+            0x3E, 0x80, 0xE0, 0x68, // BCPS = auto-increment from color 0 of palette 0
+            0x3E, 0x1F, 0xE0, 0x69, // BCPD = 0x1F, the low byte of red
+            0xAF, 0xE0, 0x69,       // BCPD = 0x00, its high byte
+            0x3E, 0x04, 0xE0, 0x4C, // KEY0 = DMG compatibility mode
+            0x3E, 0xE4, 0xE0, 0x47, // BGP
+            0x3E, 0x91, 0xE0, 0x40, // LCDC = LCD and background on
+            0x3E, 0x01, 0xE0, 0x50, // Unmap the boot ROM
+        ];
+        let mut boot_rom = vec![0; 0x900];
+        boot_rom[..boot_program.len()].copy_from_slice(&boot_program);
+
+        // The cartridge has no CGB flag and spins right after the boot ROM.
+        let mut rom = vec![0; 0x8000];
+        rom[boot_program.len()..boot_program.len() + 2].copy_from_slice(&[0x18, 0xFE]);
+        let mut bus = MemoryBus::new(Box::new(RomOnly::new(rom, Vec::new())), Model::Cgb);
+        bus.map_boot_rom(BootRom::new(Model::Cgb, boot_rom).unwrap());
+        assert!(bus.is_cgb_mode());
+
+        let mut cpu = Cpu::new(bus);
+        while cpu.bus.ppu().frame_count() < 2 {
+            cpu.step().unwrap();
+        }
+
+        assert!(!cpu.bus.is_cgb_mode());
+        let framebuffer = cpu.bus.ppu().framebuffer();
+        assert!(framebuffer.iter().all(|&color| color == 0x001F));
     }
 }
@@ -0,0 +1,49 @@
+/// An interrupt source, with variants ordered from highest to lowest priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    /// Every interrupt, from highest to lowest priority.
+    pub const ALL: [Self; 5] = [
+        Self::VBlank,
+        Self::LcdStat,
+        Self::Timer,
+        Self::Serial,
+        Self::Joypad,
+    ];
+
+    /// The interrupt's bit in the `IE` and `IF` registers.
+    pub const fn mask(self) -> u8 {
+        match self {
+            Self::VBlank => 0b0_0001,
+            Self::LcdStat => 0b0_0010,
+            Self::Timer => 0b0_0100,
+            Self::Serial => 0b0_1000,
+            Self::Joypad => 0b1_0000,
+        }
+    }
+
+    /// The address the CPU jumps to when dispatching the interrupt.
+    pub const fn vector(self) -> u16 {
+        match self {
+            Self::VBlank => 0x40,
+            Self::LcdStat => 0x48,
+            Self::Timer => 0x50,
+            Self::Serial => 0x58,
+            Self::Joypad => 0x60,
+        }
+    }
+
+    /// Returns the highest priority interrupt set in `flags`, if any.
+    pub fn highest_priority(flags: u8) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|interrupt| flags & interrupt.mask() != 0)
+    }
+}
@@ -0,0 +1,215 @@
+use super::{read_banked, write_banked, Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+/// The ROM size of every known MBC1M multicart.
+const MULTICART_ROM_SIZE: usize = 0x10_0000;
+
+/// The Nintendo logo in the header, which every game on a multicart has a copy of.
+const LOGO_START: usize = 0x104;
+const LOGO_END: usize = 0x134;
+
+/// MBC1, supporting up to 2 MiB of ROM and 32 KiB of RAM.
+///
+/// On MBC1M multicarts, `BANK2` is wired to ROM address lines one bit lower, so each game sees a
+/// separate 256 KiB slice of the ROM.
+#[derive(Debug)]
+pub struct Mbc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    /// The 5-bit `BANK1` register, holding the low bits of the ROM bank at `0x4000..=0x7FFF`.
+    bank1: u8,
+    /// The 2-bit `BANK2` register, holding the RAM bank or the upper bits of the ROM bank.
+    bank2: u8,
+    /// In mode 1, `BANK2` also applies to `0x0000..=0x3FFF` and to external RAM.
+    mode: bool,
+    is_multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: Vec<u8>, ram: Vec<u8>) -> Self {
+        let is_multicart = Self::detect_multicart(&rom);
+
+        Self {
+            rom,
+            ram,
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            mode: false,
+            is_multicart,
+        }
+    }
+
+    /// Guesses whether `rom` is an MBC1M multicart by looking for a second game's header in the
+    /// second 256 KiB slice, since the cartridge header doesn't tell them apart.
+    fn detect_multicart(rom: &[u8]) -> bool {
+        if rom.len() != MULTICART_ROM_SIZE {
+            return false;
+        }
+
+        let second_game = 0x10 * ROM_BANK_SIZE;
+
+        rom[LOGO_START..LOGO_END] == rom[second_game + LOGO_START..second_game + LOGO_END]
+    }
+
+    pub const fn is_multicart(&self) -> bool {
+        self.is_multicart
+    }
+
+    /// Returns the ROM bank bits contributed by `BANK2`.
+    const fn upper_rom_bank(&self) -> usize {
+        let shift = if self.is_multicart { 4 } else { 5 };
+
+        (self.bank2 as usize) << shift
+    }
+
+    /// Returns the ROM bank bits contributed by `BANK1`.
+    const fn lower_rom_bank(&self) -> usize {
+        let mask = if self.is_multicart { 0x0F } else { 0x1F };
+
+        (self.bank1 & mask) as usize
+    }
+
+    const fn ram_bank(&self) -> usize {
+        if self.mode {
+            self.bank2 as usize
+        } else {
+            0
+        }
+    }
+}
+
+impl Mbc for Mbc1 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF if self.mode => self.upper_rom_bank(),
+            0x0000..=0x3FFF => 0,
+            _ => self.upper_rom_bank() | self.lower_rom_bank(),
+        };
+
+        read_banked(&self.rom, ROM_BANK_SIZE, bank, address)
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // Selecting bank 0 selects bank 1 instead. The check looks at all five bits, so on
+                // larger ROMs banks 0x20, 0x40 and 0x60 can't be mapped here either.
+                let bank = value & 0x1F;
+                self.bank1 = if bank == 0 { 1 } else { bank };
+            }
+            0x4000..=0x5FFF => self.bank2 = value & 0b11,
+            _ => self.mode = value & 0b1 != 0,
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        read_banked(&self.ram, RAM_BANK_SIZE, self.ram_bank(), address)
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled {
+            let bank = self.ram_bank();
+            write_banked(&mut self.ram, RAM_BANK_SIZE, bank, address, value);
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::mbc::tests::{mapped_bank, numbered_rom};
+
+    /// Returns an MBC1M multicart with games, each with a copy of the logo, at banks 0x00, 0x10,
+    /// 0x20 and 0x30.
+    fn multicart() -> Mbc1 {
+        let mut rom = numbered_rom(MULTICART_ROM_SIZE / ROM_BANK_SIZE);
+        for game in 0..4 {
+            let start = game * 0x10 * ROM_BANK_SIZE;
+            rom[start + LOGO_START..start + LOGO_END].fill(0xCE);
+        }
+
+        Mbc1::new(rom, Vec::new())
+    }
+
+    #[test]
+    fn bank1_zero_check_covers_all_five_bits() {
+        let mut mbc = Mbc1::new(numbered_rom(0x80), Vec::new());
+
+        for (bank2, bank) in [(0, 0x01), (1, 0x21), (2, 0x41), (3, 0x61)] {
+            mbc.write_register(0x4000, bank2);
+            mbc.write_register(0x2000, 0x00);
+            assert_eq!(mapped_bank(&mbc, 0x4000), bank);
+
+            // Bits above the five BANK1 has are ignored, so this selects bank 0 as well.
+            mbc.write_register(0x2000, 0x20);
+            assert_eq!(mapped_bank(&mbc, 0x4000), bank);
+        }
+
+        mbc.write_register(0x2000, 0x1F);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0x7F);
+    }
+
+    #[test]
+    fn mode_1_banks_lower_rom_area_and_ram() {
+        let mut mbc = Mbc1::new(numbered_rom(0x80), vec![0; 4 * RAM_BANK_SIZE]);
+        mbc.write_register(0x0000, 0x0A);
+        mbc.write_register(0x4000, 0x02);
+        mbc.write_ram(0xA000, 0x42);
+        assert_eq!(mapped_bank(&mbc, 0x0000), 0, "mode 0 always maps bank 0");
+
+        mbc.write_register(0x6000, 0x01);
+        assert_eq!(mapped_bank(&mbc, 0x0000), 0x40);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0x41);
+        assert_eq!(mbc.read_ram(0xA000), 0, "RAM bank 2 is mapped now");
+
+        mbc.write_register(0x4000, 0x00);
+        assert_eq!(mbc.read_ram(0xA000), 0x42);
+    }
+
+    #[test]
+    fn multicart_shifts_bank2_by_four() {
+        let mut mbc = multicart();
+        assert!(mbc.is_multicart());
+
+        mbc.write_register(0x4000, 0x01);
+        mbc.write_register(0x2000, 0x00);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0x11);
+
+        // Only the low four BANK1 bits reach the ROM.
+        mbc.write_register(0x4000, 0x03);
+        mbc.write_register(0x2000, 0x1F);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0x3F);
+
+        mbc.write_register(0x6000, 0x01);
+        for (bank2, game) in [(0, 0x00), (1, 0x10), (2, 0x20), (3, 0x30)] {
+            mbc.write_register(0x4000, bank2);
+            assert_eq!(mapped_bank(&mbc, 0x0000), game);
+        }
+    }
+
+    #[test]
+    fn single_logo_isnt_multicart() {
+        let mut rom = numbered_rom(MULTICART_ROM_SIZE / ROM_BANK_SIZE);
+        rom[LOGO_START..LOGO_END].fill(0xCE);
+        let mut mbc = Mbc1::new(rom, Vec::new());
+        assert!(!mbc.is_multicart());
+
+        // BANK2 shifts by five bits as usual.
+        mbc.write_register(0x4000, 0x01);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0x21);
+    }
+}
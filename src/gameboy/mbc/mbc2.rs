@@ -0,0 +1,129 @@
+use super::{read_banked, Mbc, ROM_BANK_SIZE};
+
+/// The number of half-bytes of RAM built into the controller.
+const RAM_SIZE: usize = 0x200;
+
+/// Only the lower nibble of each RAM byte exists, so the upper one reads as 1s.
+const RAM_UNUSED_BITS: u8 = 0xF0;
+
+/// The address bit that decides whether a register write goes to the RAM enable or to the ROM
+/// bank.
+const REGISTER_SELECT_BIT: u16 = 0x100;
+
+/// MBC2, supporting up to 256 KiB of ROM and with 512 half-bytes of RAM built in.
+#[derive(Debug)]
+pub struct Mbc2 {
+    rom: Vec<u8>,
+    ram: [u8; RAM_SIZE],
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub const fn new(rom: Vec<u8>) -> Self {
+        Self {
+            rom,
+            ram: [0; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Mbc for Mbc2 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => usize::from(self.rom_bank),
+        };
+
+        read_banked(&self.rom, ROM_BANK_SIZE, bank, address)
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x3FFF if address & REGISTER_SELECT_BIT == 0 => {
+                self.ram_enabled = value & 0x0F == 0x0A;
+            }
+            0x0000..=0x3FFF => {
+                let bank = value & 0x0F;
+                self.rom_bank = if bank == 0 { 1 } else { bank };
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        // The RAM is mirrored across the whole external RAM area.
+        self.ram[usize::from(address) % RAM_SIZE] | RAM_UNUSED_BITS
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled {
+            self.ram[usize::from(address) % RAM_SIZE] = value & !RAM_UNUSED_BITS;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::mbc::tests::{mapped_bank, numbered_rom};
+
+    #[test]
+    fn address_bit_8_selects_register() {
+        let mut mbc = Mbc2::new(numbered_rom(0x10));
+
+        // With bit 8 clear, 0x0A is a RAM enable rather than a bank number.
+        mbc.write_register(0x0000, 0x0A);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 1);
+        mbc.write_ram(0xA000, 0x05);
+        assert_eq!(mbc.read_ram(0xA000), 0xF5);
+
+        // With bit 8 set, anywhere in 0x0000..=0x3FFF, it's a bank number.
+        mbc.write_register(0x3F00, 0x0A);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0x0A);
+        assert_eq!(mbc.read_ram(0xA000), 0xF5, "RAM stays enabled");
+
+        mbc.write_register(0x0100, 0x00);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 1, "bank 0 maps bank 1");
+
+        mbc.write_register(0x3EFF, 0x00);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn ram_holds_nibbles() {
+        let mut mbc = Mbc2::new(numbered_rom(2));
+        mbc.write_register(0x0000, 0x0A);
+
+        mbc.write_ram(0xA000, 0xAB);
+        assert_eq!(mbc.read_ram(0xA000), 0xFB);
+        assert_eq!(mbc.ram()[0], 0x0B);
+    }
+
+    #[test]
+    fn ram_echoes_every_512_bytes() {
+        let mut mbc = Mbc2::new(numbered_rom(2));
+        mbc.write_register(0x0000, 0x0A);
+        mbc.write_ram(0xA1FF, 0x03);
+        mbc.write_ram(0xBE00, 0x07);
+
+        for echo in (0xA000..0xC000).step_by(RAM_SIZE) {
+            assert_eq!(mbc.read_ram(echo), 0xF7);
+            assert_eq!(mbc.read_ram(echo + 0x1FF), 0xF3);
+        }
+    }
+}
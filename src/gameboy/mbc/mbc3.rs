@@ -0,0 +1,224 @@
+use super::{
+    read_banked,
+    rtc::{Rtc, RtcRegister},
+    write_banked, Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE,
+};
+
+/// MBC3, supporting up to 2 MiB of ROM, 32 KiB of RAM and an optional real-time clock.
+#[derive(Debug)]
+pub struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rtc: Option<Rtc>,
+    /// Enables both external RAM and the RTC registers.
+    ram_enabled: bool,
+    rom_bank: u8,
+    /// Selects a RAM bank with `0x00..=0x07`, or an RTC register with `0x08..=0x0C`.
+    ram_bank: u8,
+    /// The last value written to the latch register, since latching takes a 0 followed by a 1.
+    latch: u8,
+}
+
+impl Mbc3 {
+    pub const fn new(rom: Vec<u8>, ram: Vec<u8>, rtc: Option<Rtc>) -> Self {
+        Self {
+            rom,
+            ram,
+            rtc,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            latch: 0xFF,
+        }
+    }
+}
+
+impl Mbc for Mbc3 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => usize::from(self.rom_bank),
+        };
+
+        read_banked(&self.rom, ROM_BANK_SIZE, bank, address)
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                let bank = value & 0x7F;
+                self.rom_bank = if bank == 0 { 1 } else { bank };
+            }
+            0x4000..=0x5FFF => self.ram_bank = value,
+            _ => {
+                if self.latch == 0x00 && value == 0x01 {
+                    if let Some(rtc) = &mut self.rtc {
+                        rtc.latch();
+                    }
+                }
+
+                self.latch = value;
+            }
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        match (self.ram_bank, &self.rtc) {
+            (0x00..=0x07, _) => read_banked(
+                &self.ram,
+                RAM_BANK_SIZE,
+                usize::from(self.ram_bank),
+                address,
+            ),
+            (bank, Some(rtc)) => {
+                RtcRegister::from_bank(bank).map_or(0xFF, |register| rtc.read(register))
+            }
+            (_, None) => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        match (self.ram_bank, &mut self.rtc) {
+            (0x00..=0x07, _) => write_banked(
+                &mut self.ram,
+                RAM_BANK_SIZE,
+                usize::from(self.ram_bank),
+                address,
+                value,
+            ),
+            (bank, Some(rtc)) => {
+                if let Some(register) = RtcRegister::from_bank(bank) {
+                    rtc.write(register, value);
+                }
+            }
+            (_, None) => {}
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn rtc(&self) -> Option<&Rtc> {
+        self.rtc.as_ref()
+    }
+
+    fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        self.rtc.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::mbc::{
+        rtc::ManualClock,
+        tests::{mapped_bank, numbered_rom},
+    };
+
+    /// Returns an MBC3 with 32 KiB of RAM and a clock at `seconds`, with RAM and the RTC enabled.
+    fn mbc3_with_rtc_at(seconds: u64) -> Mbc3 {
+        let rtc = Rtc::new(Box::new(ManualClock::new(seconds)));
+        let mut mbc = Mbc3::new(numbered_rom(4), vec![0; 4 * RAM_BANK_SIZE], Some(rtc));
+        mbc.write_register(0x0000, 0x0A);
+
+        mbc
+    }
+
+    #[test]
+    fn rom_bank_zero_maps_bank_one() {
+        let mut mbc = Mbc3::new(numbered_rom(0x80), Vec::new(), None);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 1);
+
+        mbc.write_register(0x2000, 0x00);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 1);
+
+        mbc.write_register(0x2000, 0x7F);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0x7F);
+        assert_eq!(mapped_bank(&mbc, 0x0000), 0);
+    }
+
+    #[test]
+    fn ram_bank_register_selects_ram_or_rtc() {
+        let mut mbc = mbc3_with_rtc_at(0);
+        for bank in 0..4 {
+            mbc.write_register(0x4000, bank);
+            mbc.write_ram(0xA000, 0x10 + bank);
+        }
+
+        let registers = [
+            (0x08, 59),   // Seconds
+            (0x09, 58),   // Minutes
+            (0x0A, 23),   // Hours
+            (0x0B, 0xFF), // Low 8 bits of the day counter
+            (0x0C, 0x01), // Bit 8 of the day counter
+        ];
+        for (bank, value) in registers {
+            mbc.write_register(0x4000, bank);
+            mbc.write_ram(0xA000, value);
+        }
+
+        for bank in 0..4 {
+            mbc.write_register(0x4000, bank);
+            assert_eq!(mbc.read_ram(0xA000), 0x10 + bank);
+        }
+        for (bank, value) in registers {
+            mbc.write_register(0x4000, bank);
+            assert_eq!(mbc.read_ram(0xA000), value);
+        }
+
+        mbc.write_register(0x4000, 0x0D);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF, "0x0D selects nothing");
+
+        mbc.write_register(0x4000, 0x08);
+        mbc.write_register(0x0000, 0x00);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF, "RAM enable gates the RTC too");
+    }
+
+    #[test]
+    fn mbc3_latches_on_zero_then_one() {
+        let clock = ManualClock::new(1_000);
+        let rtc = Rtc::new(Box::new(clock.clone()));
+        let mut mbc = Mbc3::new(vec![0; 0x8000], vec![0; 0x2000], Some(rtc));
+        mbc.write_register(0x0000, 0x0A);
+        mbc.write_register(0x4000, 0x08);
+
+        clock.advance(5);
+        mbc.write_register(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(0xA000), 0, "a lone 1 doesn't latch");
+
+        mbc.write_register(0x6000, 0x00);
+        assert_eq!(mbc.read_ram(0xA000), 0, "a 0 alone doesn't latch");
+        mbc.write_register(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(0xA000), 5);
+
+        clock.advance(3);
+        assert_eq!(
+            mbc.read_ram(0xA000),
+            5,
+            "latched registers don't keep counting"
+        );
+        mbc.write_register(0x6000, 0x01);
+        assert_eq!(
+            mbc.read_ram(0xA000),
+            5,
+            "latching again needs another 0 first"
+        );
+        mbc.write_register(0x6000, 0x00);
+        mbc.write_register(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(0xA000), 8);
+    }
+}
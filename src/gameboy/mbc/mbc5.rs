@@ -0,0 +1,150 @@
+use super::{read_banked, write_banked, Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+/// The RAM bank register bit that drives the motor on rumble cartridges.
+const RUMBLE_BIT: u8 = 0b1000;
+
+/// MBC5, supporting up to 8 MiB of ROM, 128 KiB of RAM and an optional rumble motor.
+#[derive(Debug)]
+pub struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    /// The 9-bit ROM bank. Unlike on other controllers, bank 0 can be mapped to
+    /// `0x4000..=0x7FFF`.
+    rom_bank: u16,
+    ram_bank: u8,
+    has_rumble: bool,
+    is_rumbling: bool,
+}
+
+impl Mbc5 {
+    pub const fn new(rom: Vec<u8>, ram: Vec<u8>, has_rumble: bool) -> Self {
+        Self {
+            rom,
+            ram,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            is_rumbling: false,
+        }
+    }
+}
+
+impl Mbc for Mbc5 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => usize::from(self.rom_bank),
+        };
+
+        read_banked(&self.rom, ROM_BANK_SIZE, bank, address)
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | u16::from(value),
+            0x3000..=0x3FFF => {
+                self.rom_bank = (self.rom_bank & 0xFF) | (u16::from(value & 0b1) << 8);
+            }
+            0x4000..=0x5FFF => {
+                // On rumble cartridges, the motor takes over the top bit of the RAM bank.
+                if self.has_rumble {
+                    self.is_rumbling = value & RUMBLE_BIT != 0;
+                    self.ram_bank = value & 0b0111;
+                } else {
+                    self.ram_bank = value & 0b1111;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        read_banked(
+            &self.ram,
+            RAM_BANK_SIZE,
+            usize::from(self.ram_bank),
+            address,
+        )
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled {
+            let bank = usize::from(self.ram_bank);
+            write_banked(&mut self.ram, RAM_BANK_SIZE, bank, address, value);
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn is_rumbling(&self) -> bool {
+        self.is_rumbling
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::{
+        mbc::tests::{mapped_bank, numbered_rom},
+        memory::MemoryBus,
+        model::Model,
+    };
+
+    #[test]
+    fn rom_bank_has_nine_bits_and_bank_zero() {
+        let mut mbc = Mbc5::new(numbered_rom(0x200), Vec::new(), false);
+
+        mbc.write_register(0x2000, 0x00);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0);
+
+        mbc.write_register(0x3000, 0x01);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0x100);
+
+        mbc.write_register(0x2000, 0xFF);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0x1FF);
+
+        // Only bit 0 of the upper register exists.
+        mbc.write_register(0x3000, 0xFE);
+        assert_eq!(mapped_bank(&mbc, 0x4000), 0xFF);
+    }
+
+    #[test]
+    fn rumble_bit_is_masked_out_of_ram_bank() {
+        let ram = vec![0; 16 * RAM_BANK_SIZE];
+        let mut bus = MemoryBus::new(Box::new(Mbc5::new(numbered_rom(4), ram, true)), Model::Dmg);
+        bus.write_byte(0x0000, 0x0A);
+        bus.write_byte(0x4000, 0x01);
+        bus.write_byte(0xA000, 0x42);
+
+        bus.write_byte(0x4000, 0x09);
+        assert!(bus.is_rumbling());
+        assert_eq!(bus.read_byte(0xA000), 0x42, "still RAM bank 1");
+
+        bus.write_byte(0x4000, 0x01);
+        assert!(!bus.is_rumbling());
+    }
+
+    #[test]
+    fn ram_bank_has_four_bits_without_rumble() {
+        let mut mbc = Mbc5::new(numbered_rom(4), vec![0; 16 * RAM_BANK_SIZE], false);
+        mbc.write_register(0x0000, 0x0A);
+        mbc.write_register(0x4000, 0x09);
+        mbc.write_ram(0xA000, 0x42);
+
+        assert!(!mbc.is_rumbling());
+        assert_eq!(mbc.ram()[9 * RAM_BANK_SIZE], 0x42);
+    }
+}
@@ -0,0 +1,133 @@
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+pub mod rtc;
+
+use std::fmt;
+
+use self::rtc::Rtc;
+pub use self::{mbc1::Mbc1, mbc2::Mbc2, mbc3::Mbc3, mbc5::Mbc5};
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// A memory bank controller, which maps the cartridge's ROM and RAM into the address space.
+pub trait Mbc: fmt::Debug + Send {
+    /// Reads from the ROM area at `0x0000..=0x7FFF`.
+    fn read_rom(&self, address: u16) -> u8;
+
+    /// Handles a write to the ROM area at `0x0000..=0x7FFF`, which targets the controller's
+    /// registers rather than the ROM itself.
+    fn write_register(&mut self, address: u16, value: u8);
+
+    /// Reads from the external RAM area at `0xA000..=0xBFFF`.
+    fn read_ram(&self, address: u16) -> u8;
+
+    /// Writes to the external RAM area at `0xA000..=0xBFFF`.
+    fn write_ram(&mut self, address: u16, value: u8);
+
+    /// Returns the external RAM, including RAM built into the controller.
+    fn ram(&self) -> &[u8] {
+        &[]
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut []
+    }
+
+    /// Returns the cartridge's real-time clock, if it has one.
+    fn rtc(&self) -> Option<&Rtc> {
+        None
+    }
+
+    fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        None
+    }
+
+    /// Returns whether the cartridge's rumble motor is currently switched on.
+    fn is_rumbling(&self) -> bool {
+        false
+    }
+}
+
+/// Returns the offset of `address` within bank `bank` of a `length` byte memory, wrapping banks
+/// that are out of range the way the unconnected upper address lines do.
+fn banked_offset(length: usize, bank_size: usize, bank: usize, address: u16) -> Option<usize> {
+    if length == 0 {
+        return None;
+    }
+
+    Some((bank * bank_size + (usize::from(address) & (bank_size - 1))) % length)
+}
+
+fn read_banked(memory: &[u8], bank_size: usize, bank: usize, address: u16) -> u8 {
+    banked_offset(memory.len(), bank_size, bank, address).map_or(0xFF, |offset| memory[offset])
+}
+
+fn write_banked(memory: &mut [u8], bank_size: usize, bank: usize, address: u16, value: u8) {
+    if let Some(offset) = banked_offset(memory.len(), bank_size, bank, address) {
+        memory[offset] = value;
+    }
+}
+
+/// A cartridge without a controller, with 32 KiB of ROM and up to 8 KiB of RAM.
+#[derive(Debug)]
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub const fn new(rom: Vec<u8>, ram: Vec<u8>) -> Self {
+        Self { rom, ram }
+    }
+}
+
+impl Mbc for RomOnly {
+    fn read_rom(&self, address: u16) -> u8 {
+        self.rom.get(usize::from(address)).copied().unwrap_or(0xFF)
+    }
+
+    fn write_register(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ram(&self, address: u16) -> u8 {
+        read_banked(&self.ram, RAM_BANK_SIZE, 0, address)
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        write_banked(&mut self.ram, RAM_BANK_SIZE, 0, address, value);
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{Mbc, ROM_BANK_SIZE};
+
+    /// Returns a ROM of `banks` banks, each starting with its own bank number as a little-endian
+    /// word.
+    pub(crate) fn numbered_rom(banks: usize) -> Vec<u8> {
+        let mut rom = vec![0; banks * ROM_BANK_SIZE];
+        for (bank, data) in rom.chunks_exact_mut(ROM_BANK_SIZE).enumerate() {
+            data[..2].copy_from_slice(&(bank as u16).to_le_bytes());
+        }
+
+        rom
+    }
+
+    /// Returns the number of the ROM bank `mbc` maps at `address`, the start of either ROM area.
+    pub(crate) fn mapped_bank(mbc: &dyn Mbc, address: u16) -> usize {
+        usize::from(u16::from_le_bytes([
+            mbc.read_rom(address),
+            mbc.read_rom(address + 1),
+        ]))
+    }
+}
@@ -0,0 +1,384 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The size of the RTC state in the format shared by most emulators, which is appended to the
+/// cartridge RAM in save files.
+pub const RTC_STATE_SIZE: usize = 48;
+
+/// The size of the older variant of the format, with a 32-bit timestamp.
+const LEGACY_RTC_STATE_SIZE: usize = 44;
+
+const DAY_HIGH_BIT: u8 = 0b0000_0001;
+const HALT_BIT: u8 = 0b0100_0000;
+const DAY_CARRY_BIT: u8 = 0b1000_0000;
+
+/// A source of wall-clock time for the RTC.
+pub trait Clock: fmt::Debug + Send {
+    /// Returns the number of seconds since the Unix epoch.
+    fn now(&self) -> u64;
+}
+
+/// The host's wall clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs())
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a test can keep one to
+/// fast-forward the clock it handed to the RTC.
+#[derive(Debug, Default, Clone)]
+pub struct ManualClock {
+    seconds: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(seconds: u64) -> Self {
+        Self {
+            seconds: Arc::new(AtomicU64::new(seconds)),
+        }
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.seconds.fetch_add(seconds, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, seconds: u64) {
+        self.seconds.store(seconds, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.seconds.load(Ordering::Relaxed)
+    }
+}
+
+/// One of the RTC registers, selected by writing `0x08..=0x0C` to the RAM bank register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcRegister {
+    Seconds,
+    Minutes,
+    Hours,
+    DayLow,
+    /// Bit 0 of the day counter, the halt flag in bit 6 and the day carry in bit 7.
+    DayHigh,
+}
+
+impl RtcRegister {
+    pub const fn from_bank(bank: u8) -> Option<Self> {
+        match bank {
+            0x08 => Some(Self::Seconds),
+            0x09 => Some(Self::Minutes),
+            0x0A => Some(Self::Hours),
+            0x0B => Some(Self::DayLow),
+            0x0C => Some(Self::DayHigh),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct RtcRegisters {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    /// The 9-bit day counter.
+    days: u16,
+    halted: bool,
+    day_carry: bool,
+}
+
+impl RtcRegisters {
+    fn read(&self, register: RtcRegister) -> u8 {
+        match register {
+            RtcRegister::Seconds => self.seconds,
+            RtcRegister::Minutes => self.minutes,
+            RtcRegister::Hours => self.hours,
+            RtcRegister::DayLow => (self.days & 0xFF) as u8,
+            RtcRegister::DayHigh => {
+                let mut value = ((self.days >> 8) as u8) & DAY_HIGH_BIT;
+                if self.halted {
+                    value |= HALT_BIT;
+                }
+                if self.day_carry {
+                    value |= DAY_CARRY_BIT;
+                }
+
+                value
+            }
+        }
+    }
+
+    fn write(&mut self, register: RtcRegister, value: u8) {
+        match register {
+            RtcRegister::Seconds => self.seconds = value & 0x3F,
+            RtcRegister::Minutes => self.minutes = value & 0x3F,
+            RtcRegister::Hours => self.hours = value & 0x1F,
+            RtcRegister::DayLow => self.days = (self.days & 0x100) | u16::from(value),
+            RtcRegister::DayHigh => {
+                self.days = (self.days & 0xFF) | (u16::from(value & DAY_HIGH_BIT) << 8);
+                self.halted = value & HALT_BIT != 0;
+                self.day_carry = value & DAY_CARRY_BIT != 0;
+            }
+        }
+    }
+
+    /// Moves the clock forward by `elapsed` seconds. Once the day counter overflows, it wraps
+    /// around and sets the day carry, which stays set until cleared by a write.
+    fn advance(&mut self, elapsed: u64) {
+        let total = u64::from(self.seconds) + elapsed;
+        self.seconds = (total % 60) as u8;
+
+        let total = u64::from(self.minutes) + total / 60;
+        self.minutes = (total % 60) as u8;
+
+        let total = u64::from(self.hours) + total / 60;
+        self.hours = (total % 24) as u8;
+
+        let total = u64::from(self.days) + total / 24;
+        self.days = (total % 512) as u16;
+        if total >= 512 {
+            self.day_carry = true;
+        }
+    }
+
+    fn save(&self, bytes: &mut [u8]) {
+        let registers = [
+            RtcRegister::Seconds,
+            RtcRegister::Minutes,
+            RtcRegister::Hours,
+            RtcRegister::DayLow,
+            RtcRegister::DayHigh,
+        ];
+
+        for (chunk, register) in bytes.chunks_exact_mut(4).zip(registers) {
+            chunk.copy_from_slice(&u32::from(self.read(register)).to_le_bytes());
+        }
+    }
+
+    fn load(bytes: &[u8]) -> Self {
+        let registers = [
+            RtcRegister::Seconds,
+            RtcRegister::Minutes,
+            RtcRegister::Hours,
+            RtcRegister::DayLow,
+            RtcRegister::DayHigh,
+        ];
+
+        let mut loaded = Self::default();
+        for (chunk, register) in bytes.chunks_exact(4).zip(registers) {
+            loaded.write(register, chunk[0]);
+        }
+
+        loaded
+    }
+}
+
+/// The MBC3 real-time clock.
+///
+/// The registers are brought up to date lazily from the clock source whenever they are latched
+/// or written, so no time is lost while the emulator is paused or not running at all.
+#[derive(Debug)]
+pub struct Rtc {
+    clock: Box<dyn Clock>,
+    current: RtcRegisters,
+    latched: RtcRegisters,
+    /// The clock time `current` was last brought up to date at.
+    last_update: u64,
+}
+
+impl Rtc {
+    pub fn new(clock: Box<dyn Clock>) -> Self {
+        let last_update = clock.now();
+
+        Self {
+            clock,
+            current: RtcRegisters::default(),
+            latched: RtcRegisters::default(),
+            last_update,
+        }
+    }
+
+    /// Replaces the clock source, counting time from the new clock's present onwards.
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.update();
+
+        self.last_update = clock.now();
+        self.clock = clock;
+    }
+
+    fn update(&mut self) {
+        let now = self.clock.now();
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+
+        if !self.current.halted {
+            self.current.advance(elapsed);
+        }
+    }
+
+    /// Copies the running clock into the registers the CPU reads.
+    pub fn latch(&mut self) {
+        self.update();
+
+        self.latched = self.current;
+    }
+
+    pub fn read(&self, register: RtcRegister) -> u8 {
+        self.latched.read(register)
+    }
+
+    pub fn write(&mut self, register: RtcRegister, value: u8) {
+        self.update();
+
+        self.current.write(register, value);
+        self.latched.write(register, value);
+    }
+
+    /// Serializes the clock in the 48-byte format used by most emulators: the running and the
+    /// latched registers as little-endian 32-bit words, followed by a 64-bit Unix timestamp.
+    pub fn save_state(&self) -> [u8; RTC_STATE_SIZE] {
+        let mut bytes = [0; RTC_STATE_SIZE];
+        self.current.save(&mut bytes[0..20]);
+        self.latched.save(&mut bytes[20..40]);
+        bytes[40..48].copy_from_slice(&self.last_update.to_le_bytes());
+
+        bytes
+    }
+
+    /// Restores a clock saved by [`Self::save_state`], or by an emulator using the older 44-byte
+    /// variant with a 32-bit timestamp. The time passed since it was saved is caught up on.
+    ///
+    /// Returns `false` and leaves the clock untouched if `bytes` has neither size.
+    pub fn load_state(&mut self, bytes: &[u8]) -> bool {
+        let last_update = match bytes.len() {
+            RTC_STATE_SIZE => u64::from_le_bytes(bytes[40..48].try_into().unwrap_or_default()),
+            LEGACY_RTC_STATE_SIZE => u64::from(u32::from_le_bytes(
+                bytes[40..44].try_into().unwrap_or_default(),
+            )),
+            _ => return false,
+        };
+
+        self.current = RtcRegisters::load(&bytes[0..20]);
+        self.latched = RtcRegisters::load(&bytes[20..40]);
+        self.last_update = last_update;
+        self.update();
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+    fn rtc_at(seconds: u64) -> (Rtc, ManualClock) {
+        let clock = ManualClock::new(seconds);
+
+        (Rtc::new(Box::new(clock.clone())), clock)
+    }
+
+    #[test]
+    fn registers_roll_over() {
+        let mut registers = RtcRegisters {
+            seconds: 59,
+            minutes: 59,
+            hours: 23,
+            days: 0xFF,
+            ..RtcRegisters::default()
+        };
+        registers.advance(1);
+
+        assert_eq!(
+            registers,
+            RtcRegisters {
+                days: 0x100,
+                ..RtcRegisters::default()
+            }
+        );
+        assert_eq!(registers.read(RtcRegister::DayLow), 0x00);
+        assert_eq!(registers.read(RtcRegister::DayHigh), DAY_HIGH_BIT);
+    }
+
+    #[test]
+    fn day_carry_sticks_until_cleared() {
+        let mut registers = RtcRegisters {
+            days: 511,
+            ..RtcRegisters::default()
+        };
+        registers.advance(SECONDS_PER_DAY);
+        assert_eq!(registers.days, 0);
+        assert!(registers.day_carry);
+
+        registers.advance(SECONDS_PER_DAY);
+        assert_eq!(registers.days, 1);
+        assert!(registers.day_carry, "the carry stays set without a write");
+
+        registers.write(RtcRegister::DayHigh, 0x00);
+        assert!(!registers.day_carry);
+    }
+
+    #[test]
+    fn halted_clock_stands_still() {
+        let (mut rtc, clock) = rtc_at(0);
+        rtc.write(RtcRegister::DayHigh, HALT_BIT);
+        clock.advance(100);
+        rtc.latch();
+
+        assert_eq!(rtc.read(RtcRegister::Seconds), 0);
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let (mut rtc, clock) = rtc_at(1_000);
+        clock.advance(SECONDS_PER_DAY + 3_723);
+        rtc.latch();
+        let state = rtc.save_state();
+
+        let (mut restored, _) = rtc_at(1_000 + SECONDS_PER_DAY + 3_723);
+        assert!(restored.load_state(&state));
+        assert_eq!(restored.save_state(), state);
+        assert_eq!(restored.read(RtcRegister::Hours), 1);
+        assert_eq!(restored.read(RtcRegister::Minutes), 2);
+        assert_eq!(restored.read(RtcRegister::Seconds), 3);
+        assert_eq!(restored.read(RtcRegister::DayLow), 1);
+    }
+
+    #[test]
+    fn legacy_state_round_trips_and_catches_up() {
+        let (mut rtc, clock) = rtc_at(1_000);
+        clock.advance(90);
+        rtc.latch();
+        let mut legacy = [0; LEGACY_RTC_STATE_SIZE];
+        legacy.copy_from_slice(&rtc.save_state()[..LEGACY_RTC_STATE_SIZE]);
+
+        // The restoring clock is a minute ahead of the saved timestamp.
+        let (mut restored, _) = rtc_at(1_150);
+        assert!(restored.load_state(&legacy));
+        restored.latch();
+        assert_eq!(restored.read(RtcRegister::Minutes), 2);
+        assert_eq!(restored.read(RtcRegister::Seconds), 30);
+        assert_eq!(restored.save_state()[40..48], 1_150_u64.to_le_bytes());
+    }
+
+    #[test]
+    fn state_of_wrong_size_is_rejected() {
+        let (mut rtc, _) = rtc_at(0);
+
+        assert!(!rtc.load_state(&[0; 47]));
+    }
+}
@@ -0,0 +1,750 @@
+use super::{
+    boot::{BootRom, BOOT_ROM_DISABLE_ADDRESS},
+    cartridge::header::CGB_FLAG_ADDRESS,
+    interrupt::Interrupt,
+    mbc::{rtc::Rtc, Mbc},
+    model::Model,
+    ppu::{Mode, Ppu},
+    timer::{Timer, DIV_ADDRESS, TAC_ADDRESS},
+};
+
+const VRAM_START: u16 = 0x8000;
+const WRAM_START: u16 = 0xC000;
+const ECHO_RAM_START: u16 = 0xE000;
+const IO_START: u16 = 0xFF00;
+const HRAM_START: u16 = 0xFF80;
+
+const WRAM_BANK_SIZE: usize = 0x1000;
+const DMG_WRAM_SIZE: usize = 2 * WRAM_BANK_SIZE;
+const CGB_WRAM_SIZE: usize = 8 * WRAM_BANK_SIZE;
+const IO_SIZE: usize = 0x80;
+const HRAM_SIZE: usize = 0x7F;
+
+const INTERRUPT_FLAG_ADDRESS: u16 = 0xFF0F;
+const OAM_DMA_ADDRESS: u16 = 0xFF46;
+const KEY0_ADDRESS: u16 = 0xFF4C;
+const KEY1_ADDRESS: u16 = 0xFF4D;
+const HDMA1_ADDRESS: u16 = 0xFF51;
+const HDMA2_ADDRESS: u16 = 0xFF52;
+const HDMA3_ADDRESS: u16 = 0xFF53;
+const HDMA4_ADDRESS: u16 = 0xFF54;
+const HDMA5_ADDRESS: u16 = 0xFF55;
+const SVBK_ADDRESS: u16 = 0xFF70;
+const INTERRUPT_ENABLE_ADDRESS: u16 = 0xFFFF;
+
+/// The unused upper bits of `IF` always read as 1.
+const INTERRUPT_FLAG_UNUSED_BITS: u8 = 0b1110_0000;
+
+/// Set by the CGB boot ROM to run a cartridge without CGB support in DMG compatibility mode.
+const KEY0_DMG_COMPATIBILITY_FLAG: u8 = 1 << 2;
+
+const KEY1_DOUBLE_SPEED_FLAG: u8 = 1 << 7;
+const KEY1_SWITCH_ARMED_FLAG: u8 = 1 << 0;
+const KEY1_UNUSED_BITS: u8 = 0b0111_1110;
+
+const SVBK_BANK_MASK: u8 = 0b0000_0111;
+const SVBK_UNUSED_BITS: u8 = 0b1111_1000;
+
+const T_CYCLES_PER_M_CYCLE: u32 = 4;
+const OAM_START: u16 = 0xFE00;
+const OAM_DMA_LENGTH: u16 = 0xA0;
+
+const HDMA5_HBLANK_FLAG: u8 = 1 << 7;
+const HDMA5_LENGTH_MASK: u8 = 0b0111_1111;
+/// The source and destination are aligned to 16 bytes, the size of each block HDMA copies.
+const HDMA_ALIGNMENT_MASK: u16 = 0xFFF0;
+const HDMA_DESTINATION_MASK: u16 = 0x1FF0;
+const HDMA_DESTINATION_WRAP_MASK: u16 = 0x1FFF;
+const HDMA_BLOCK_SIZE: u16 = 0x10;
+/// How long the CPU is halted for each block, in M-cycles at normal speed.
+const HDMA_BLOCK_M_CYCLES: u32 = 8;
+/// How long the CPU stalls while switching speeds.
+const SPEED_SWITCH_M_CYCLES: u32 = 2050;
+
+/// The I/O registers as the DMG boot ROM leaves them, as `(address, value)` pairs.
+const DMG_POST_BOOT_IO: [(u16, u8); 25] = [
+    (0xFF00, 0xCF), // P1
+    (0xFF01, 0x00), // SB
+    (0xFF02, 0x7E), // SC
+    (0xFF10, 0x80), // NR10
+    (0xFF11, 0xBF), // NR11
+    (0xFF12, 0xF3), // NR12
+    (0xFF13, 0xFF), // NR13
+    (0xFF14, 0xBF), // NR14
+    (0xFF16, 0x3F), // NR21
+    (0xFF17, 0x00), // NR22
+    (0xFF18, 0xFF), // NR23
+    (0xFF19, 0xBF), // NR24
+    (0xFF1A, 0x7F), // NR30
+    (0xFF1B, 0xFF), // NR31
+    (0xFF1C, 0x9F), // NR32
+    (0xFF1D, 0xFF), // NR33
+    (0xFF1E, 0xBF), // NR34
+    (0xFF20, 0xFF), // NR41
+    (0xFF21, 0x00), // NR42
+    (0xFF22, 0x00), // NR43
+    (0xFF23, 0xBF), // NR44
+    (0xFF24, 0x77), // NR50
+    (0xFF25, 0xF3), // NR51
+    (0xFF26, 0xF1), // NR52
+    (0xFF46, 0xFF), // DMA
+];
+
+/// The registers whose post-boot value on the SGB differs from the DMG's.
+const SGB_POST_BOOT_IO: [(u16, u8); 1] = [
+    (0xFF26, 0xF0), // NR52
+];
+
+/// The registers whose post-boot value on the CGB differs from the DMG's.
+const CGB_POST_BOOT_IO: [(u16, u8); 2] = [
+    (0xFF02, 0x7F), // SC
+    (0xFF46, 0x00), // DMA
+];
+
+/// The CPU's view of the address space, routing every access to the component behind it.
+#[derive(Debug)]
+pub struct MemoryBus {
+    model: Model,
+    cgb_mode: bool,
+    mbc: Box<dyn Mbc>,
+    boot_rom: Option<BootRom>,
+    ppu: Ppu,
+    timer: Timer,
+    /// Eight 4 KiB banks on CGB hardware, of which `0xD000..=0xDFFF` maps the one `SVBK` selects
+    /// in CGB mode, and two otherwise.
+    wram: Box<[u8]>,
+    wram_bank: u8,
+    io: [u8; IO_SIZE],
+    hram: [u8; HRAM_SIZE],
+    interrupt_enable: u8,
+    interrupt_flag: u8,
+    oam_dma: Option<OamDma>,
+    hdma: Hdma,
+    /// Whether the CPU is in `HALT`, which pauses HBlank DMA.
+    is_cpu_halted: bool,
+    double_speed: bool,
+    speed_switch_armed: bool,
+    /// M-cycles the CPU has to sit out, for the current instruction to take longer than usual.
+    stall_m_cycles: u32,
+}
+
+impl MemoryBus {
+    pub fn new(mbc: Box<dyn Mbc>, model: Model) -> Self {
+        let cgb_mode = model.is_cgb() && supports_cgb(mbc.as_ref());
+
+        let mut ppu = Ppu::new(model);
+        ppu.set_cgb_mode(cgb_mode);
+
+        Self {
+            model,
+            cgb_mode,
+            mbc,
+            boot_rom: None,
+            ppu,
+            timer: Timer::new(),
+            wram: vec![
+                0;
+                if model.is_cgb() {
+                    CGB_WRAM_SIZE
+                } else {
+                    DMG_WRAM_SIZE
+                }
+            ]
+            .into_boxed_slice(),
+            wram_bank: 1,
+            io: [0; IO_SIZE],
+            hram: [0; HRAM_SIZE],
+            interrupt_enable: 0,
+            interrupt_flag: 0,
+            oam_dma: None,
+            hdma: Hdma::default(),
+            is_cpu_halted: false,
+            double_speed: false,
+            speed_switch_armed: false,
+            stall_m_cycles: 0,
+        }
+    }
+
+    pub const fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Returns whether CGB features are available, which requires both CGB hardware and a
+    /// cartridge that supports them.
+    pub const fn is_cgb_mode(&self) -> bool {
+        self.cgb_mode
+    }
+
+    /// Overlays `boot_rom` on the cartridge ROM until the program writes to `0xFF50`. The CGB
+    /// boot ROM always runs in CGB mode, and picks the cartridge's mode itself.
+    pub fn map_boot_rom(&mut self, boot_rom: BootRom) {
+        self.boot_rom = Some(boot_rom);
+        self.set_cgb_mode(self.model.is_cgb());
+    }
+
+    fn set_cgb_mode(&mut self, cgb_mode: bool) {
+        self.cgb_mode = cgb_mode;
+        self.ppu.set_cgb_mode(cgb_mode);
+        if !cgb_mode {
+            self.wram_bank = 1;
+        }
+    }
+
+    pub const fn is_boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+
+    /// Puts the I/O registers in the state the model's boot ROM leaves them in, for starting
+    /// straight at the cartridge's entry point.
+    pub(crate) fn apply_post_boot_state(&mut self) {
+        let overrides: &[(u16, u8)] = match self.model {
+            Model::Dmg0 | Model::Dmg | Model::Mgb => &[],
+            Model::Sgb | Model::Sgb2 => &SGB_POST_BOOT_IO,
+            Model::Cgb | Model::Agb => &CGB_POST_BOOT_IO,
+        };
+        for &(address, value) in DMG_POST_BOOT_IO.iter().chain(overrides) {
+            self.io[usize::from(address - IO_START)] = value;
+        }
+
+        self.ppu.apply_post_boot_state();
+        self.timer.apply_post_boot_state(self.model);
+        self.interrupt_flag = 0x01;
+        self.interrupt_enable = 0x00;
+        self.io[usize::from(BOOT_ROM_DISABLE_ADDRESS - IO_START)] = 0x01;
+    }
+
+    pub const fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    pub fn ppu_mut(&mut self) -> &mut Ppu {
+        &mut self.ppu
+    }
+
+    /// Returns whether the CGB is running in double speed mode.
+    pub const fn is_double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    /// Switches between normal and double speed if the program armed a switch through `KEY1`,
+    /// which is how `STOP` behaves then. Switching also resets `DIV`.
+    pub(crate) fn switch_speed(&mut self) {
+        if self.cgb_mode && self.speed_switch_armed {
+            self.double_speed = !self.double_speed;
+            self.speed_switch_armed = false;
+            self.stall_m_cycles += SPEED_SWITCH_M_CYCLES;
+            self.timer.reset_divider();
+        }
+    }
+
+    pub(crate) fn set_cpu_halted(&mut self, is_halted: bool) {
+        self.is_cpu_halted = is_halted;
+    }
+
+    /// Returns and clears the M-cycles the CPU has to stall for.
+    pub(crate) fn take_stall(&mut self) -> u32 {
+        std::mem::take(&mut self.stall_m_cycles)
+    }
+
+    /// Advances the components that run alongside the CPU by `m_cycles` M-cycles, returning how
+    /// many T-cycles of normal speed time passed. In double speed mode an M-cycle is half as
+    /// long, so the PPU, which keeps its pace, sees half as many dots go by.
+    pub(crate) fn tick(&mut self, m_cycles: u32) -> u32 {
+        let t_cycles = if self.double_speed {
+            m_cycles * T_CYCLES_PER_M_CYCLE / 2
+        } else {
+            m_cycles * T_CYCLES_PER_M_CYCLE
+        };
+        self.interrupt_flag |= self.ppu.step(t_cycles);
+        self.interrupt_flag |= self.timer.step(m_cycles);
+        // HBlanks that pass while the CPU is halted don't copy anything, so the transfer only
+        // picks up again after it wakes.
+        for _ in 0..self.ppu.take_hblanks_started() {
+            if self.hdma.is_hblank_active && !self.is_cpu_halted {
+                self.copy_hdma_block();
+            }
+        }
+        for _ in 0..m_cycles {
+            self.step_oam_dma();
+        }
+
+        t_cycles
+    }
+
+    /// Returns whether an OAM DMA transfer currently holds the bus.
+    pub fn is_oam_dma_active(&self) -> bool {
+        self.oam_dma.is_some_and(|dma| dma.is_copying())
+    }
+
+    /// Copies the next byte of the running OAM DMA transfer, if there is one.
+    fn step_oam_dma(&mut self) {
+        let Some(mut dma) = self.oam_dma else {
+            return;
+        };
+
+        if dma.delay > 0 {
+            dma.delay -= 1;
+        } else {
+            dma.last_byte = self.read_unconflicted(dma.source + dma.offset);
+            self.ppu.write_oam(OAM_START + dma.offset, dma.last_byte);
+            dma.offset += 1;
+        }
+        self.oam_dma = (dma.offset < OAM_DMA_LENGTH).then_some(dma);
+    }
+
+    /// Returns the cartridge's external RAM.
+    pub fn cartridge_ram(&self) -> &[u8] {
+        self.mbc.ram()
+    }
+
+    pub fn cartridge_ram_mut(&mut self) -> &mut [u8] {
+        self.mbc.ram_mut()
+    }
+
+    /// Returns the cartridge's real-time clock, if it has one.
+    pub fn rtc(&self) -> Option<&Rtc> {
+        self.mbc.rtc()
+    }
+
+    pub fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        self.mbc.rtc_mut()
+    }
+
+    /// Returns whether the cartridge's rumble motor is currently switched on, for the host to
+    /// poll once per frame.
+    pub fn is_rumbling(&self) -> bool {
+        self.mbc.is_rumbling()
+    }
+
+    /// Sets `interrupt`'s bit in `IF`, so it is dispatched once enabled in `IE`.
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag |= interrupt.mask();
+    }
+
+    /// Returns the interrupts that are both requested and enabled.
+    pub(crate) const fn pending_interrupts(&self) -> u8 {
+        self.interrupt_enable & self.interrupt_flag & !INTERRUPT_FLAG_UNUSED_BITS
+    }
+
+    pub(crate) fn acknowledge_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag &= !interrupt.mask();
+    }
+
+    /// Starts a VRAM DMA transfer as requested through `HDMA5`, or cancels the running HBlank
+    /// DMA.
+    fn start_hdma(&mut self, byte: u8) {
+        if self.hdma.is_hblank_active && byte & HDMA5_HBLANK_FLAG == 0 {
+            self.hdma.is_hblank_active = false;
+            return;
+        }
+
+        self.hdma.length = byte & HDMA5_LENGTH_MASK;
+        if byte & HDMA5_HBLANK_FLAG != 0 {
+            self.hdma.is_hblank_active = true;
+            // Starting in the middle of an HBlank copies its block right away rather than waiting
+            // for the next one.
+            if self.ppu.is_lcd_enabled() && self.ppu.mode() == Mode::HBlank {
+                self.copy_hdma_block();
+            }
+        } else {
+            // General purpose DMA copies everything at once, halting the CPU until it's done.
+            while self.copy_hdma_block() {}
+        }
+    }
+
+    /// Copies the next 16 bytes of the running VRAM DMA transfer, returning whether there are
+    /// more left.
+    fn copy_hdma_block(&mut self) -> bool {
+        for _ in 0..HDMA_BLOCK_SIZE {
+            let byte = self.read_unconflicted(self.hdma.source);
+            self.ppu
+                .write_vram(VRAM_START + self.hdma.destination, byte);
+            self.hdma.source = self.hdma.source.wrapping_add(1);
+            self.hdma.destination = (self.hdma.destination + 1) & HDMA_DESTINATION_WRAP_MASK;
+        }
+
+        // The transfer takes as long in double speed mode, which fits twice as many M-cycles.
+        self.stall_m_cycles += if self.double_speed {
+            HDMA_BLOCK_M_CYCLES * 2
+        } else {
+            HDMA_BLOCK_M_CYCLES
+        };
+
+        self.hdma.length = self.hdma.length.wrapping_sub(1) & HDMA5_LENGTH_MASK;
+        let is_done = self.hdma.length == HDMA5_LENGTH_MASK;
+        if is_done {
+            self.hdma.is_hblank_active = false;
+        }
+
+        !is_done
+    }
+
+    /// Returns where `address`, within `0xC000..=0xDFFF`, lands in WRAM, given the bank `SVBK`
+    /// selects.
+    fn wram_offset(&self, address: u16) -> usize {
+        let offset = usize::from(address - WRAM_START);
+        if offset < WRAM_BANK_SIZE {
+            offset
+        } else {
+            usize::from(self.wram_bank) * WRAM_BANK_SIZE + offset - WRAM_BANK_SIZE
+        }
+    }
+
+    /// Reads `address` as the CPU sees it. While OAM DMA holds the bus, only `0xFF00..=0xFFFF`
+    /// stays reachable, and every other read returns the byte the transfer is copying.
+    pub fn read_byte(&self, address: u16) -> u8 {
+        if let Some(dma) = self.oam_dma.filter(OamDma::is_copying) {
+            if address < IO_START {
+                return dma.last_byte;
+            }
+        }
+
+        self.read_unconflicted(address)
+    }
+
+    fn read_unconflicted(&self, address: u16) -> u8 {
+        if let Some(byte) = self.boot_rom.as_ref().and_then(|rom| rom.read(address)) {
+            return byte;
+        }
+
+        match address {
+            0x0000..=0x7FFF => self.mbc.read_rom(address),
+            0x8000..=0x9FFF => self.ppu.read_vram(address),
+            0xA000..=0xBFFF => self.mbc.read_ram(address),
+            0xC000..=0xDFFF => self.wram[self.wram_offset(address)],
+            // Echo RAM mirrors 0xC000..=0xDDFF, banking included.
+            0xE000..=0xFDFF => self.wram[self.wram_offset(address - ECHO_RAM_START + WRAM_START)],
+            0xFE00..=0xFE9F => self.ppu.read_oam(address),
+            0xFEA0..=0xFEFF => self.model.unusable_area_byte(address),
+            DIV_ADDRESS..=TAC_ADDRESS => self.timer.read_register(address),
+            INTERRUPT_FLAG_ADDRESS => self.interrupt_flag | INTERRUPT_FLAG_UNUSED_BITS,
+            KEY1_ADDRESS if self.cgb_mode => {
+                let mut key1 = KEY1_UNUSED_BITS;
+                if self.double_speed {
+                    key1 |= KEY1_DOUBLE_SPEED_FLAG;
+                }
+                if self.speed_switch_armed {
+                    key1 |= KEY1_SWITCH_ARMED_FLAG;
+                }
+
+                key1
+            }
+            KEY1_ADDRESS => 0xFF,
+            HDMA5_ADDRESS if self.cgb_mode => {
+                if self.hdma.is_hblank_active {
+                    self.hdma.length
+                } else {
+                    HDMA5_HBLANK_FLAG | self.hdma.length
+                }
+            }
+            HDMA1_ADDRESS..=HDMA5_ADDRESS => 0xFF,
+            SVBK_ADDRESS if self.cgb_mode => SVBK_UNUSED_BITS | self.wram_bank,
+            SVBK_ADDRESS => 0xFF,
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B | 0xFF4F | 0xFF68..=0xFF6B => {
+                self.ppu.read_register(address)
+            }
+            0xFF00..=0xFF7F => self.io[usize::from(address - IO_START)],
+            0xFF80..=0xFFFE => self.hram[usize::from(address - HRAM_START)],
+            INTERRUPT_ENABLE_ADDRESS => self.interrupt_enable,
+        }
+    }
+
+    /// Writes `byte` to `address` as the CPU would, which OAM DMA blocks for everything below
+    /// `0xFF00`.
+    pub fn write_byte(&mut self, address: u16, byte: u8) {
+        if self.is_oam_dma_active() && address < IO_START {
+            return;
+        }
+
+        match address {
+            0x0000..=0x7FFF => self.mbc.write_register(address, byte),
+            0x8000..=0x9FFF => self.ppu.write_vram(address, byte),
+            0xA000..=0xBFFF => self.mbc.write_ram(address, byte),
+            0xC000..=0xDFFF => self.wram[self.wram_offset(address)] = byte,
+            0xE000..=0xFDFF => {
+                self.wram[self.wram_offset(address - ECHO_RAM_START + WRAM_START)] = byte;
+            }
+            0xFE00..=0xFE9F => self.ppu.write_oam(address, byte),
+            0xFEA0..=0xFEFF => {}
+            DIV_ADDRESS..=TAC_ADDRESS => self.timer.write_register(address, byte),
+            INTERRUPT_FLAG_ADDRESS => self.interrupt_flag = byte & !INTERRUPT_FLAG_UNUSED_BITS,
+            // Only the switch can be armed; the current speed bit is read-only.
+            KEY1_ADDRESS if self.cgb_mode => {
+                self.speed_switch_armed = byte & KEY1_SWITCH_ARMED_FLAG != 0;
+            }
+            KEY1_ADDRESS => {}
+            HDMA1_ADDRESS if self.cgb_mode => {
+                self.hdma.source = u16::from(byte) << 8 | self.hdma.source & 0x00FF;
+            }
+            HDMA2_ADDRESS if self.cgb_mode => {
+                self.hdma.source =
+                    self.hdma.source & 0xFF00 | u16::from(byte) & HDMA_ALIGNMENT_MASK;
+            }
+            HDMA3_ADDRESS if self.cgb_mode => {
+                self.hdma.destination =
+                    (u16::from(byte) << 8 | self.hdma.destination & 0x00FF) & HDMA_DESTINATION_MASK;
+            }
+            HDMA4_ADDRESS if self.cgb_mode => {
+                self.hdma.destination =
+                    (self.hdma.destination & 0xFF00 | u16::from(byte)) & HDMA_DESTINATION_MASK;
+            }
+            HDMA5_ADDRESS if self.cgb_mode => self.start_hdma(byte),
+            HDMA1_ADDRESS..=HDMA5_ADDRESS => {}
+            // Bank 0 is always mapped at 0xC000, so selecting it maps bank 1 instead.
+            SVBK_ADDRESS if self.cgb_mode => self.wram_bank = (byte & SVBK_BANK_MASK).max(1),
+            SVBK_ADDRESS => {}
+            OAM_DMA_ADDRESS => {
+                self.io[usize::from(address - IO_START)] = byte;
+                self.oam_dma = Some(OamDma::new(u16::from(byte) << 8));
+            }
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B | 0xFF4F | 0xFF68..=0xFF6B => {
+                self.ppu.write_register(address, byte)
+            }
+            // Only the boot ROM gets to pick the mode, and the palettes it set up for it stay.
+            KEY0_ADDRESS if self.cgb_mode && self.is_boot_rom_mapped() => {
+                self.io[usize::from(address - IO_START)] = byte;
+                if byte & KEY0_DMG_COMPATIBILITY_FLAG != 0 {
+                    self.set_cgb_mode(false);
+                }
+            }
+            KEY0_ADDRESS => {}
+            BOOT_ROM_DISABLE_ADDRESS => {
+                // Once unmapped, the boot ROM stays unmapped until the next reset, leaving the
+                // cartridge in DMG compatibility mode unless it supports CGB mode.
+                if byte & 0b1 != 0 && self.is_boot_rom_mapped() {
+                    self.boot_rom = None;
+                    self.io[usize::from(address - IO_START)] = 0x01;
+                    self.set_cgb_mode(self.cgb_mode && supports_cgb(self.mbc.as_ref()));
+                }
+            }
+            0xFF00..=0xFF7F => self.io[usize::from(address - IO_START)] = byte,
+            0xFF80..=0xFFFE => self.hram[usize::from(address - HRAM_START)] = byte,
+            INTERRUPT_ENABLE_ADDRESS => self.interrupt_enable = byte,
+        }
+    }
+}
+
+/// Returns whether the cartridge declares CGB support, without which CGB hardware falls back to
+/// DMG compatibility mode.
+fn supports_cgb(mbc: &dyn Mbc) -> bool {
+    mbc.read_rom(CGB_FLAG_ADDRESS as u16) & 0x80 != 0
+}
+
+/// The CGB's VRAM DMA, which copies 16-byte blocks into VRAM either all at once or one block
+/// per HBlank.
+#[derive(Debug, Clone, Copy)]
+struct Hdma {
+    source: u16,
+    /// The destination's offset into VRAM.
+    destination: u16,
+    /// The number of blocks left minus one, as `HDMA5` reports it.
+    length: u8,
+    is_hblank_active: bool,
+}
+
+impl Default for Hdma {
+    fn default() -> Self {
+        Self {
+            source: 0,
+            destination: 0,
+            length: HDMA5_LENGTH_MASK,
+            is_hblank_active: false,
+        }
+    }
+}
+
+/// A transfer of 160 bytes into OAM, one per M-cycle, started by writing the source's high byte
+/// to `DMA`.
+#[derive(Debug, Clone, Copy)]
+struct OamDma {
+    source: u16,
+    offset: u16,
+    /// M-cycles left before the first byte is copied.
+    delay: u8,
+    /// The byte copied last, which is what the CPU reads while the transfer holds the bus.
+    last_byte: u8,
+}
+
+impl OamDma {
+    const fn new(source: u16) -> Self {
+        // Sources from echo RAM upwards read WRAM, even past where echo RAM ends.
+        let source = if source >= ECHO_RAM_START {
+            source - (ECHO_RAM_START - WRAM_START)
+        } else {
+            source
+        };
+
+        Self {
+            source,
+            offset: 0,
+            delay: 1,
+            last_byte: 0xFF,
+        }
+    }
+
+    const fn is_copying(&self) -> bool {
+        self.delay == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::mbc::RomOnly;
+
+    /// Returns the bus of a CGB running a cartridge with CGB support.
+    fn cgb_bus() -> MemoryBus {
+        let mut rom = vec![0; 0x8000];
+        rom[CGB_FLAG_ADDRESS] = 0x80;
+
+        MemoryBus::new(Box::new(RomOnly::new(rom, Vec::new())), Model::Cgb)
+    }
+
+    #[test]
+    fn speed_switch_resets_div_and_disarms() {
+        let mut bus = cgb_bus();
+        bus.tick(1_000);
+        assert_ne!(bus.read_byte(DIV_ADDRESS), 0);
+
+        bus.write_byte(KEY1_ADDRESS, KEY1_SWITCH_ARMED_FLAG);
+        assert_eq!(bus.read_byte(KEY1_ADDRESS) & 0x81, KEY1_SWITCH_ARMED_FLAG);
+
+        bus.switch_speed();
+        assert!(bus.is_double_speed());
+        assert_eq!(bus.read_byte(KEY1_ADDRESS) & 0x81, KEY1_DOUBLE_SPEED_FLAG);
+        assert_eq!(bus.read_byte(DIV_ADDRESS), 0);
+        assert_eq!(bus.take_stall(), SPEED_SWITCH_M_CYCLES);
+    }
+
+    #[test]
+    fn oam_dma_holds_bus_for_160_m_cycles() {
+        let mut bus = MemoryBus::new(
+            Box::new(RomOnly::new(vec![0; 0x8000], Vec::new())),
+            Model::Dmg,
+        );
+        for offset in 0..OAM_DMA_LENGTH {
+            bus.write_byte(WRAM_START + offset, offset as u8);
+        }
+        bus.write_byte(HRAM_START, 0x42);
+
+        bus.write_byte(OAM_DMA_ADDRESS, 0xC0);
+        assert!(
+            !bus.is_oam_dma_active(),
+            "the transfer starts an M-cycle later"
+        );
+
+        for m_cycle in 0..OAM_DMA_LENGTH {
+            bus.tick(1);
+            assert!(bus.is_oam_dma_active());
+
+            // Everything below 0xFF00 reads the byte being copied, and ignores writes.
+            let last_byte = if m_cycle == 0 {
+                0xFF
+            } else {
+                m_cycle as u8 - 1
+            };
+            assert_eq!(bus.read_byte(WRAM_START + 0x100), last_byte);
+            assert_eq!(bus.read_byte(0x0000), last_byte);
+            bus.write_byte(WRAM_START + 0x100, 0x77);
+
+            assert_eq!(bus.read_byte(HRAM_START), 0x42, "HRAM stays reachable");
+        }
+
+        bus.tick(1);
+        assert!(!bus.is_oam_dma_active());
+        assert_eq!(bus.read_byte(WRAM_START + 0x100), 0x00);
+        for offset in 0..OAM_DMA_LENGTH {
+            assert_eq!(bus.read_byte(OAM_START + offset), offset as u8);
+        }
+    }
+
+    /// Fills `0xC000..0xC040` with a pattern and points HDMA from there to `0x8000`.
+    fn prepare_hdma(bus: &mut MemoryBus) {
+        for offset in 0..0x40 {
+            bus.write_byte(WRAM_START + offset, 0x80 | offset as u8);
+        }
+        bus.write_byte(HDMA1_ADDRESS, 0xC0);
+        bus.write_byte(HDMA2_ADDRESS, 0x00);
+        bus.write_byte(HDMA3_ADDRESS, 0x80);
+        bus.write_byte(HDMA4_ADDRESS, 0x00);
+    }
+
+    /// Returns how many bytes from the start of VRAM hold the pattern `prepare_hdma` copies.
+    fn copied_bytes(bus: &MemoryBus) -> u16 {
+        (0..0x40)
+            .take_while(|&offset| bus.read_byte(VRAM_START + offset) == 0x80 | offset as u8)
+            .count() as u16
+    }
+
+    /// Ticks `bus` until the PPU enters `mode`.
+    fn tick_until(bus: &mut MemoryBus, mode: Mode) {
+        while bus.ppu().mode() == mode {
+            bus.tick(1);
+        }
+        while bus.ppu().mode() != mode {
+            bus.tick(1);
+        }
+    }
+
+    #[test]
+    fn general_purpose_dma_copies_everything_at_once() {
+        let mut bus = cgb_bus();
+        prepare_hdma(&mut bus);
+
+        bus.write_byte(HDMA5_ADDRESS, 0x02);
+        assert_eq!(copied_bytes(&bus), 0x30);
+        assert_eq!(bus.read_byte(HDMA5_ADDRESS), 0xFF);
+        assert_eq!(bus.take_stall(), 3 * HDMA_BLOCK_M_CYCLES);
+    }
+
+    #[test]
+    fn hblank_dma_copies_a_block_per_hblank() {
+        let mut bus = cgb_bus();
+        prepare_hdma(&mut bus);
+        bus.write_byte(0xFF40, 0x91);
+        tick_until(&mut bus, Mode::OamScan);
+
+        bus.write_byte(HDMA5_ADDRESS, HDMA5_HBLANK_FLAG | 0x02);
+        assert_eq!(copied_bytes(&bus), 0);
+
+        // HDMA5 counts down the blocks left minus one, ending at 0x7F.
+        for (copied, length) in [(0x10, 0x01), (0x20, 0x00), (0x30, 0x7F)] {
+            tick_until(&mut bus, Mode::HBlank);
+            assert_eq!(copied_bytes(&bus), copied);
+            assert_eq!(bus.read_byte(HDMA5_ADDRESS) & HDMA5_LENGTH_MASK, length);
+            assert_eq!(bus.take_stall(), HDMA_BLOCK_M_CYCLES);
+        }
+
+        tick_until(&mut bus, Mode::HBlank);
+        assert_eq!(bus.take_stall(), 0, "the transfer is done");
+    }
+
+    #[test]
+    fn hblank_dma_started_in_hblank_copies_right_away() {
+        let mut bus = cgb_bus();
+        prepare_hdma(&mut bus);
+        bus.write_byte(0xFF40, 0x91);
+        tick_until(&mut bus, Mode::HBlank);
+
+        bus.write_byte(HDMA5_ADDRESS, HDMA5_HBLANK_FLAG | 0x02);
+        assert_eq!(copied_bytes(&bus), 0x10);
+    }
+
+    #[test]
+    fn hblank_dma_pauses_while_halted() {
+        let mut bus = cgb_bus();
+        prepare_hdma(&mut bus);
+        bus.write_byte(0xFF40, 0x91);
+        tick_until(&mut bus, Mode::OamScan);
+        bus.write_byte(HDMA5_ADDRESS, HDMA5_HBLANK_FLAG | 0x02);
+
+        bus.set_cpu_halted(true);
+        tick_until(&mut bus, Mode::HBlank);
+        tick_until(&mut bus, Mode::HBlank);
+        assert_eq!(copied_bytes(&bus), 0);
+
+        bus.set_cpu_halted(false);
+        tick_until(&mut bus, Mode::HBlank);
+        assert_eq!(copied_bytes(&bus), 0x10);
+    }
+}
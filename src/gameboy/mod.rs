@@ -1 +1,9 @@
+pub mod boot;
+pub mod cartridge;
 pub mod cpu;
+pub mod interrupt;
+pub mod mbc;
+pub mod memory;
+pub mod model;
+pub mod ppu;
+pub mod timer;
@@ -0,0 +1,120 @@
+use std::{fmt, str::FromStr};
+
+use super::cartridge::header::{CgbSupport, Header};
+
+/// The Game Boy hardware being emulated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// The earliest revision of the original Game Boy, with its own boot ROM.
+    Dmg0,
+    /// The original Game Boy.
+    #[default]
+    Dmg,
+    /// The Game Boy Pocket.
+    Mgb,
+    /// The Super Game Boy.
+    Sgb,
+    /// The Super Game Boy 2.
+    Sgb2,
+    /// The Game Boy Color.
+    Cgb,
+    /// The Game Boy Advance, running Game Boy software.
+    Agb,
+}
+
+impl Model {
+    pub const ALL: [Self; 7] = [
+        Self::Dmg0,
+        Self::Dmg,
+        Self::Mgb,
+        Self::Sgb,
+        Self::Sgb2,
+        Self::Cgb,
+        Self::Agb,
+    ];
+
+    /// Picks the model a cartridge is best played on: a CGB for cartridges that use its
+    /// features, a DMG for everything else.
+    pub const fn for_cartridge(header: &Header) -> Self {
+        match header.cgb_support {
+            CgbSupport::Enhanced | CgbSupport::Required => Self::Cgb,
+            CgbSupport::None => Self::Dmg,
+        }
+    }
+
+    /// Returns whether the model has CGB hardware, and so can run in CGB mode.
+    pub const fn is_cgb(self) -> bool {
+        matches!(self, Self::Cgb | Self::Agb)
+    }
+
+    pub const fn is_sgb(self) -> bool {
+        matches!(self, Self::Sgb | Self::Sgb2)
+    }
+
+    /// Returns the size of the model's boot ROM in bytes.
+    pub const fn boot_rom_size(self) -> usize {
+        if self.is_cgb() {
+            0x900
+        } else {
+            0x100
+        }
+    }
+
+    /// Returns what reading the unusable area at `0xFEA0..=0xFEFF` yields on this model.
+    pub const fn unusable_area_byte(self, address: u16) -> u8 {
+        match self {
+            // The AGB repeats the high nibble of the address's low byte.
+            Self::Agb => {
+                let nibble = (address as u8) & 0xF0;
+
+                nibble | nibble >> 4
+            }
+            _ => 0x00,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Dmg0 => "DMG0",
+            Self::Dmg => "DMG",
+            Self::Mgb => "MGB",
+            Self::Sgb => "SGB",
+            Self::Sgb2 => "SGB2",
+            Self::Cgb => "CGB",
+            Self::Agb => "AGB",
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The name given isn't one of the emulated models.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownModelError(pub String);
+
+impl fmt::Display for UnknownModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown model \"{}\"! (expected one of DMG0, DMG, MGB, SGB, SGB2, CGB, AGB)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownModelError {}
+
+impl FromStr for Model {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|model| model.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownModelError(name.to_owned()))
+    }
+}
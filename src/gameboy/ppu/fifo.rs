@@ -0,0 +1,288 @@
+use std::collections::VecDeque;
+
+use super::{
+    sprite::X_OFFSET, BgPixel, ObjPixel, Ppu, LCDC_BG_TILE_MAP, LCDC_OBJ_ENABLE,
+    LCDC_WINDOW_TILE_MAP, SCREEN_WIDTH, WINDOW_X_OFFSET,
+};
+
+/// Each fetcher step takes two dots.
+const FETCH_STEP_DOTS: u32 = 2;
+/// The first tile of each line is fetched twice and the first copy thrown away.
+const DISCARDED_FETCH_DOTS: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FetchStep {
+    TileIndex,
+    DataLow,
+    DataHigh,
+    /// Waiting for the background FIFO to empty so the fetched tile can be pushed.
+    Push,
+}
+
+/// The state of the pixel FIFO renderer within the current line.
+#[derive(Debug, Clone)]
+pub(super) struct PixelFifo {
+    background: VecDeque<BgPixel>,
+    /// Sprite pixels, lined up with the front of the background FIFO.
+    objects: VecDeque<ObjPixel>,
+    step: FetchStep,
+    step_dots: u32,
+    /// The tile column the fetcher reads next, counted from the left edge of the screen or the
+    /// window.
+    fetch_x: u8,
+    tile_index: u8,
+    tile_attributes: u8,
+    tile_low: u8,
+    tile_high: u8,
+    /// The screen column the next pixel goes to.
+    x: u8,
+    /// Pixels still to be thrown away, for fine scrolling.
+    discard: u8,
+    /// Dots to wait before the fetcher starts.
+    stall: u32,
+    in_window: bool,
+    /// The fine scroll the line started with, which decides how long sprite fetches wait.
+    fine_scroll: u8,
+    /// The index into the line's sprites of the sprite being fetched.
+    pending_sprite: Option<usize>,
+    /// Dots left until the pending sprite has been fetched.
+    sprite_dots: u32,
+    /// One bit per line sprite, set once it has been fetched.
+    fetched_sprites: u16,
+    /// One bit per background tile in which a sprite fetch already waited for the fetcher.
+    waited_tiles: u32,
+}
+
+impl PixelFifo {
+    pub(super) fn new() -> Self {
+        Self {
+            background: VecDeque::with_capacity(16),
+            objects: VecDeque::with_capacity(16),
+            step: FetchStep::TileIndex,
+            step_dots: 0,
+            fetch_x: 0,
+            tile_index: 0,
+            tile_attributes: 0,
+            tile_low: 0,
+            tile_high: 0,
+            x: 0,
+            discard: 0,
+            stall: 0,
+            in_window: false,
+            fine_scroll: 0,
+            pending_sprite: None,
+            sprite_dots: 0,
+            fetched_sprites: 0,
+            waited_tiles: 0,
+        }
+    }
+
+    /// Prepares for a new line scrolled `fine_scroll` pixels into its first tile.
+    pub(super) fn reset(&mut self, fine_scroll: u8) {
+        *self = Self {
+            background: std::mem::take(&mut self.background),
+            objects: std::mem::take(&mut self.objects),
+            discard: fine_scroll,
+            fine_scroll,
+            stall: DISCARDED_FETCH_DOTS,
+            ..Self::new()
+        };
+        self.background.clear();
+        self.objects.clear();
+    }
+
+    fn restart_fetcher(&mut self) {
+        self.step = FetchStep::TileIndex;
+        self.step_dots = 0;
+    }
+}
+
+/// The pixel FIFO renderer pushes one pixel per dot, fetching tiles and sprites as it goes, so
+/// register writes during mode 3 take effect mid-line just like on hardware.
+impl Ppu {
+    /// Runs the renderer for one dot of mode 3 and returns whether the line is complete.
+    pub(super) fn tick_fifo(&mut self) -> bool {
+        if self.fifo.stall > 0 {
+            self.fifo.stall -= 1;
+
+            return false;
+        }
+
+        if self.fifo.pending_sprite.is_none() && self.lcdc & LCDC_OBJ_ENABLE != 0 {
+            if let Some(index) = self.next_sprite() {
+                // The background fetcher and pixel output both pause while the sprite is fetched.
+                self.fifo.pending_sprite = Some(index);
+                self.fifo.sprite_dots = Self::sprite_fetch_dots(
+                    &self.line_sprites[index],
+                    self.fifo.fine_scroll,
+                    &mut self.fifo.waited_tiles,
+                );
+            }
+        }
+
+        if let Some(index) = self.fifo.pending_sprite {
+            self.fifo.sprite_dots -= 1;
+            if self.fifo.sprite_dots == 0 {
+                self.merge_sprite(index);
+                self.fifo.fetched_sprites |= 1 << index;
+                self.fifo.pending_sprite = None;
+            }
+
+            return false;
+        }
+
+        // Fine scrolling finishes before the window can start, even at the left edge.
+        if !self.fifo.in_window
+            && self.fifo.discard == 0
+            && self.is_window_visible()
+            && self.fifo.x + WINDOW_X_OFFSET >= self.wx
+        {
+            // Switching to the window throws away the background pixels and starts fetching
+            // the window's tiles from its left edge.
+            self.fifo.in_window = true;
+            self.fifo.background.clear();
+            self.fifo.fetch_x = 0;
+            self.fifo.discard = WINDOW_X_OFFSET.saturating_sub(self.wx);
+            self.fifo.restart_fetcher();
+        }
+
+        self.step_fetcher();
+
+        let Some(bg) = self.fifo.background.pop_front() else {
+            return false;
+        };
+
+        // Discarded pixels never reach the screen, so they leave the sprite pixels in place.
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+
+            return false;
+        }
+        let object = self.fifo.objects.pop_front().unwrap_or_default();
+
+        let color = self.mix_pixel(bg, object);
+        let x = usize::from(self.fifo.x);
+        self.framebuffer[usize::from(self.ly) * SCREEN_WIDTH + x] = color;
+
+        self.fifo.x += 1;
+        if usize::from(self.fifo.x) < SCREEN_WIDTH {
+            return false;
+        }
+
+        if self.fifo.in_window {
+            self.window_line += 1;
+        }
+
+        true
+    }
+
+    /// Returns the first sprite, in drawing priority order, that starts at or before the next
+    /// screen column and hasn't been fetched yet.
+    fn next_sprite(&self) -> Option<usize> {
+        self.line_sprites
+            .iter()
+            .enumerate()
+            .find(|&(index, sprite)| {
+                self.fifo.fetched_sprites & (1 << index) == 0 && sprite.x <= self.fifo.x + X_OFFSET
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// Runs the background fetcher for one dot.
+    fn step_fetcher(&mut self) {
+        if self.fifo.step == FetchStep::Push {
+            if self.fifo.background.is_empty() {
+                for column in 0..8 {
+                    self.fifo.background.push_back(Self::bg_pixel(
+                        self.fifo.tile_low,
+                        self.fifo.tile_high,
+                        self.fifo.tile_attributes,
+                        column,
+                    ));
+                }
+
+                self.fifo.fetch_x = self.fifo.fetch_x.wrapping_add(1);
+                self.fifo.restart_fetcher();
+            }
+
+            return;
+        }
+
+        self.fifo.step_dots += 1;
+        if self.fifo.step_dots < FETCH_STEP_DOTS {
+            return;
+        }
+        self.fifo.step_dots = 0;
+
+        self.fifo.step = match self.fifo.step {
+            FetchStep::TileIndex => {
+                (self.fifo.tile_index, self.fifo.tile_attributes) = self.fetch_tile_map_entry();
+
+                FetchStep::DataLow
+            }
+            FetchStep::DataLow => {
+                self.fifo.tile_low = self.fetch_tile_row().0;
+
+                FetchStep::DataHigh
+            }
+            FetchStep::DataHigh => {
+                self.fifo.tile_high = self.fetch_tile_row().1;
+
+                FetchStep::Push
+            }
+            FetchStep::Push => FetchStep::Push,
+        };
+    }
+
+    /// Reads the tile map entry under the fetcher, with the scroll registers as they are now.
+    fn fetch_tile_map_entry(&self) -> (u8, u8) {
+        if self.fifo.in_window {
+            self.tile_map_entry(
+                self.lcdc & LCDC_WINDOW_TILE_MAP != 0,
+                usize::from(self.fifo.fetch_x),
+                usize::from(self.window_line / 8),
+            )
+        } else {
+            self.tile_map_entry(
+                self.lcdc & LCDC_BG_TILE_MAP != 0,
+                usize::from(self.scx / 8) + usize::from(self.fifo.fetch_x),
+                usize::from(self.scy.wrapping_add(self.ly) / 8),
+            )
+        }
+    }
+
+    /// Reads both bit planes of the fetched tile's row on the current line.
+    fn fetch_tile_row(&self) -> (u8, u8) {
+        let y = if self.fifo.in_window {
+            self.window_line
+        } else {
+            self.scy.wrapping_add(self.ly)
+        };
+
+        self.bg_tile_row(self.fifo.tile_index, self.fifo.tile_attributes, y)
+    }
+
+    /// Lays the current line's `index`th sprite over the sprite FIFO. Pixels already there stay
+    /// unless they're transparent or, in CGB mode, from a sprite later in OAM.
+    fn merge_sprite(&mut self, index: usize) {
+        let sprite = self.line_sprites[index];
+        let pixels = self.sprite_pixels(&sprite);
+        // Columns left of the current screen position are never drawn.
+        let skipped = usize::from((self.fifo.x + X_OFFSET).saturating_sub(sprite.x));
+
+        for (slot, &pixel) in pixels.iter().skip(skipped).enumerate() {
+            match self.fifo.objects.get_mut(slot) {
+                Some(existing)
+                    if existing.color_id == 0
+                        || (self.cgb_mode
+                            && pixel.color_id != 0
+                            && pixel.oam_index < existing.oam_index) =>
+                {
+                    *existing = pixel;
+                }
+                Some(_) => {}
+                None => self.fifo.objects.push_back(pixel),
+            }
+        }
+    }
+}
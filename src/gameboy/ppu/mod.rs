@@ -0,0 +1,936 @@
+mod fifo;
+mod palette;
+mod scanline;
+mod sprite;
+
+pub use self::palette::Color;
+use self::{
+    fifo::PixelFifo,
+    palette::{PaletteRam, DMG_SHADES, WHITE},
+    sprite::{Sprite, SPRITE_COUNT, X_OFFSET, Y_OFFSET},
+};
+use super::{interrupt::Interrupt, model::Model};
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+const VRAM_START: u16 = 0x8000;
+const OAM_START: u16 = 0xFE00;
+
+const VRAM_BANK_SIZE: usize = 0x2000;
+/// VRAM has a second bank on the CGB, selected through `VBK`.
+const VRAM_SIZE: usize = VRAM_BANK_SIZE * 2;
+const OAM_SIZE: usize = 0xA0;
+
+const LCDC_ADDRESS: u16 = 0xFF40;
+const STAT_ADDRESS: u16 = 0xFF41;
+const SCY_ADDRESS: u16 = 0xFF42;
+const SCX_ADDRESS: u16 = 0xFF43;
+const LY_ADDRESS: u16 = 0xFF44;
+const LYC_ADDRESS: u16 = 0xFF45;
+const BGP_ADDRESS: u16 = 0xFF47;
+const OBP0_ADDRESS: u16 = 0xFF48;
+const OBP1_ADDRESS: u16 = 0xFF49;
+const WY_ADDRESS: u16 = 0xFF4A;
+const WX_ADDRESS: u16 = 0xFF4B;
+const VBK_ADDRESS: u16 = 0xFF4F;
+const BCPS_ADDRESS: u16 = 0xFF68;
+const BCPD_ADDRESS: u16 = 0xFF69;
+const OCPS_ADDRESS: u16 = 0xFF6A;
+const OCPD_ADDRESS: u16 = 0xFF6B;
+
+/// Only bit 0 of `VBK` is used; the rest reads as 1.
+const VBK_UNUSED_BITS: u8 = 0b1111_1110;
+
+const LCDC_BG_WINDOW_ENABLE: u8 = 1 << 0;
+const LCDC_OBJ_ENABLE: u8 = 1 << 1;
+const LCDC_OBJ_SIZE: u8 = 1 << 2;
+const LCDC_BG_TILE_MAP: u8 = 1 << 3;
+const LCDC_TILE_DATA: u8 = 1 << 4;
+const LCDC_WINDOW_ENABLE: u8 = 1 << 5;
+const LCDC_WINDOW_TILE_MAP: u8 = 1 << 6;
+const LCDC_LCD_ENABLE: u8 = 1 << 7;
+
+const STAT_COINCIDENCE_FLAG: u8 = 1 << 2;
+const STAT_HBLANK_INTERRUPT: u8 = 1 << 3;
+const STAT_VBLANK_INTERRUPT: u8 = 1 << 4;
+const STAT_OAM_INTERRUPT: u8 = 1 << 5;
+const STAT_LYC_INTERRUPT: u8 = 1 << 6;
+/// Only the interrupt source selection bits of `STAT` are writable.
+const STAT_WRITABLE_BITS: u8 = 0b0111_1000;
+const STAT_UNUSED_BITS: u8 = 0b1000_0000;
+
+/// The two tile maps, as offsets into VRAM.
+const TILE_MAP_0: usize = 0x1800;
+const TILE_MAP_1: usize = 0x1C00;
+/// The base of the signed tile data addressing mode, as an offset into VRAM.
+const SIGNED_TILE_DATA_BASE: usize = 0x1000;
+const TILE_MAP_WIDTH: usize = 32;
+const BYTES_PER_TILE: usize = 16;
+
+/// The CGB keeps an attribute byte for each tile map entry at the same address in VRAM bank 1.
+const BG_ATTRIBUTE_PALETTE: u8 = 0b0000_0111;
+const BG_ATTRIBUTE_VRAM_BANK: u8 = 1 << 3;
+const BG_ATTRIBUTE_X_FLIP: u8 = 1 << 5;
+const BG_ATTRIBUTE_Y_FLIP: u8 = 1 << 6;
+const BG_ATTRIBUTE_PRIORITY: u8 = 1 << 7;
+
+/// The most sprites the OAM scan selects for a single line.
+const MAX_SPRITES_PER_LINE: usize = 10;
+
+/// `WX` holds the window's X position plus 7.
+const WINDOW_X_OFFSET: u8 = 7;
+
+const DOTS_PER_LINE: u32 = 456;
+const OAM_SCAN_DOTS: u32 = 80;
+/// Mode 3 takes this long with no scrolling, window or sprites.
+const MIN_DRAWING_DOTS: u32 = 172;
+/// The stall for fetching the window's first tile.
+const WINDOW_FETCH_DOTS: u32 = 6;
+/// The stall for fetching a sprite, on top of waiting for the background fetcher.
+const SPRITE_FETCH_DOTS: u32 = 6;
+/// The most dots a sprite can wait for the background fetcher to finish its tile.
+const MAX_SPRITE_WAIT_DOTS: u32 = 5;
+const VISIBLE_LINES: u8 = SCREEN_HEIGHT as u8;
+const LINES_PER_FRAME: u8 = 154;
+/// `LY` already reads 0 a few dots into the last line of the frame.
+const LAST_LINE: u8 = LINES_PER_FRAME - 1;
+const LAST_LINE_LY_RESET_DOT: u32 = 4;
+/// How long a frame takes, in dots, which are T-cycles at normal speed.
+pub const DOTS_PER_FRAME: u32 = DOTS_PER_LINE * LINES_PER_FRAME as u32;
+
+/// What the PPU is doing, as reported in the low bits of `STAT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+/// How the PPU turns VRAM into pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Renderer {
+    /// Draws each line in one go at the end of mode 3. Fast, but misses register writes made
+    /// while the line is being drawn.
+    #[default]
+    Scanline,
+    /// Draws one pixel per dot through the background and sprite FIFOs, like the hardware, so
+    /// mid-line register writes take effect where they should.
+    PixelFifo,
+}
+
+/// One frame of 15-bit colors, row by row. Models without color palettes show the DMG's four
+/// shades of gray.
+pub type Framebuffer = [Color; SCREEN_WIDTH * SCREEN_HEIGHT];
+
+/// A background or window pixel on its way to the screen.
+#[derive(Debug, Default, Clone, Copy)]
+struct BgPixel {
+    color_id: u8,
+    /// The CGB palette; always 0 outside CGB mode.
+    palette: u8,
+    /// Whether the tile's CGB attributes put it over sprites.
+    has_priority: bool,
+}
+
+/// A sprite pixel on its way to the screen.
+#[derive(Debug, Default, Clone, Copy)]
+struct ObjPixel {
+    /// 0 for a transparent pixel.
+    color_id: u8,
+    /// The CGB palette, or which of `OBP0` and `OBP1` to use outside CGB mode.
+    palette: u8,
+    behind_background: bool,
+    oam_index: u8,
+}
+
+/// The pixel processing unit, which owns VRAM and OAM and draws the screen line by line.
+#[derive(Debug, Clone)]
+pub struct Ppu {
+    /// Whether the PPU is CGB hardware, which colors even DMG games through its palette RAM.
+    cgb_hardware: bool,
+    cgb_mode: bool,
+    /// The renderer [`Self::set_renderer`] asked for, which takes over once the next line starts
+    /// drawing.
+    renderer: Renderer,
+    /// The renderer drawing the current line.
+    line_renderer: Renderer,
+    fifo: PixelFifo,
+    vram: [u8; VRAM_SIZE],
+    oam: [u8; OAM_SIZE],
+    vram_bank: u8,
+    bg_palettes: PaletteRam,
+    obj_palettes: PaletteRam,
+    lcdc: u8,
+    /// The interrupt source selection bits of `STAT`; the rest is derived from the PPU's state.
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+    mode: Mode,
+    /// The line being drawn, which `LY` only differs from on the last line of the frame.
+    line: u8,
+    /// The dot within the current line.
+    dot: u32,
+    /// How many dots mode 3 lasts on the current line.
+    drawing_dots: u32,
+    /// Whether any `STAT` interrupt source is active. Interrupts only fire when this goes high.
+    stat_line: bool,
+    /// Whether `LY` has matched `WY` this frame, which the window needs to be drawn at all.
+    window_y_triggered: bool,
+    /// The window's own line counter, which only advances on lines where the window is drawn.
+    window_line: u8,
+    /// The sprites the OAM scan selected for the current line, in drawing priority order.
+    line_sprites: Vec<Sprite>,
+    framebuffer: Box<Framebuffer>,
+    frame_count: u64,
+    /// How often mode 0 was entered since the bus last asked, which paces HBlank DMA.
+    hblanks_started: u32,
+}
+
+impl Ppu {
+    /// Creates the PPU of `model`, starting out without the CGB features until
+    /// [`Self::set_cgb_mode`] turns them on.
+    pub fn new(model: Model) -> Self {
+        Self {
+            cgb_hardware: model.is_cgb(),
+            cgb_mode: false,
+            renderer: Renderer::default(),
+            line_renderer: Renderer::default(),
+            fifo: PixelFifo::new(),
+            vram: [0; VRAM_SIZE],
+            oam: [0; OAM_SIZE],
+            vram_bank: 0,
+            bg_palettes: PaletteRam::new(),
+            obj_palettes: PaletteRam::new(),
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            mode: Mode::HBlank,
+            line: 0,
+            dot: 0,
+            drawing_dots: MIN_DRAWING_DOTS,
+            stat_line: false,
+            window_y_triggered: false,
+            window_line: 0,
+            line_sprites: Vec::with_capacity(MAX_SPRITES_PER_LINE),
+            framebuffer: Box::new([WHITE; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            frame_count: 0,
+            hblanks_started: 0,
+        }
+    }
+
+    pub const fn renderer(&self) -> Renderer {
+        self.renderer
+    }
+
+    /// Switches renderers, taking effect from the next line.
+    pub fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
+    }
+
+    /// Returns the last completed frame.
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    pub const fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub const fn is_lcd_enabled(&self) -> bool {
+        self.lcdc & LCDC_LCD_ENABLE != 0
+    }
+
+    /// Returns the number of frames completed so far, for the host to notice a new one.
+    pub const fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Switches the CGB features on or off. CGB hardware runs its boot ROM in CGB mode and only
+    /// falls back to DMG compatibility mode once it has set up the palettes of a DMG game, so the
+    /// mode changes while the machine is running.
+    pub(crate) fn set_cgb_mode(&mut self, cgb_mode: bool) {
+        self.cgb_mode = cgb_mode && self.cgb_hardware;
+        if !self.cgb_mode {
+            self.vram_bank = 0;
+        }
+    }
+
+    /// Puts the LCD registers in the state the boot ROM leaves them in, which hands over on the
+    /// last line of a frame.
+    pub(crate) fn apply_post_boot_state(&mut self) {
+        self.lcdc = 0x91;
+        self.stat = 0x00;
+        self.mode = Mode::VBlank;
+        self.line = LAST_LINE;
+        self.ly = 0;
+        self.dot = 400;
+        self.bgp = 0xFC;
+        self.obp0 = 0xFF;
+        self.obp1 = 0xFF;
+
+        if self.cgb_mode {
+            self.bg_palettes.fill(WHITE);
+        } else if self.cgb_hardware {
+            // Without a boot ROM to pick a colorization, DMG games keep their shades of gray.
+            self.bg_palettes.set_palette(0, DMG_SHADES);
+            self.obj_palettes.set_palette(0, DMG_SHADES);
+            self.obj_palettes.set_palette(1, DMG_SHADES);
+        }
+    }
+
+    pub(crate) fn read_vram(&self, address: u16) -> u8 {
+        self.vram[self.vram_offset(address)]
+    }
+
+    pub(crate) fn write_vram(&mut self, address: u16, byte: u8) {
+        self.vram[self.vram_offset(address)] = byte;
+    }
+
+    /// Returns where `address` lands in VRAM, given the bank `VBK` selects.
+    fn vram_offset(&self, address: u16) -> usize {
+        usize::from(self.vram_bank) * VRAM_BANK_SIZE + usize::from(address - VRAM_START)
+    }
+
+    pub(crate) fn read_oam(&self, address: u16) -> u8 {
+        self.oam[usize::from(address - OAM_START)]
+    }
+
+    pub(crate) fn write_oam(&mut self, address: u16, byte: u8) {
+        self.oam[usize::from(address - OAM_START)] = byte;
+    }
+
+    pub(crate) fn read_register(&self, address: u16) -> u8 {
+        match address {
+            LCDC_ADDRESS => self.lcdc,
+            STAT_ADDRESS => {
+                let coincidence = if self.ly == self.lyc {
+                    STAT_COINCIDENCE_FLAG
+                } else {
+                    0
+                };
+
+                STAT_UNUSED_BITS | self.stat | coincidence | self.mode as u8
+            }
+            SCY_ADDRESS => self.scy,
+            SCX_ADDRESS => self.scx,
+            LY_ADDRESS => self.ly,
+            LYC_ADDRESS => self.lyc,
+            BGP_ADDRESS => self.bgp,
+            OBP0_ADDRESS => self.obp0,
+            OBP1_ADDRESS => self.obp1,
+            WY_ADDRESS => self.wy,
+            WX_ADDRESS => self.wx,
+            VBK_ADDRESS if self.cgb_mode => VBK_UNUSED_BITS | self.vram_bank,
+            BCPS_ADDRESS if self.cgb_mode => self.bg_palettes.read_specification(),
+            BCPD_ADDRESS if self.cgb_mode => self.bg_palettes.read_data(),
+            OCPS_ADDRESS if self.cgb_mode => self.obj_palettes.read_specification(),
+            OCPD_ADDRESS if self.cgb_mode => self.obj_palettes.read_data(),
+            _ => 0xFF,
+        }
+    }
+
+    pub(crate) fn write_register(&mut self, address: u16, byte: u8) {
+        match address {
+            LCDC_ADDRESS => self.write_lcdc(byte),
+            STAT_ADDRESS => self.stat = byte & STAT_WRITABLE_BITS,
+            SCY_ADDRESS => self.scy = byte,
+            SCX_ADDRESS => self.scx = byte,
+            LYC_ADDRESS => self.lyc = byte,
+            BGP_ADDRESS => self.bgp = byte,
+            OBP0_ADDRESS => self.obp0 = byte,
+            OBP1_ADDRESS => self.obp1 = byte,
+            WY_ADDRESS => self.wy = byte,
+            WX_ADDRESS => self.wx = byte,
+            VBK_ADDRESS if self.cgb_mode => self.vram_bank = byte & 0b1,
+            BCPS_ADDRESS if self.cgb_mode => self.bg_palettes.write_specification(byte),
+            BCPD_ADDRESS if self.cgb_mode => self.bg_palettes.write_data(byte),
+            OCPS_ADDRESS if self.cgb_mode => self.obj_palettes.write_specification(byte),
+            OCPD_ADDRESS if self.cgb_mode => self.obj_palettes.write_data(byte),
+            // `LY` is read-only, and the CGB registers don't exist outside CGB mode.
+            _ => {}
+        }
+    }
+
+    fn write_lcdc(&mut self, byte: u8) {
+        let was_enabled = self.lcdc & LCDC_LCD_ENABLE != 0;
+        self.lcdc = byte;
+
+        if was_enabled && byte & LCDC_LCD_ENABLE == 0 {
+            // Turning the LCD off resets it to the top of the screen, which shows blank until
+            // it is turned back on.
+            self.mode = Mode::HBlank;
+            self.line = 0;
+            self.ly = 0;
+            self.dot = 0;
+            self.stat_line = false;
+            self.window_y_triggered = false;
+            self.window_line = 0;
+            self.framebuffer.fill(WHITE);
+        } else if !was_enabled && byte & LCDC_LCD_ENABLE != 0 {
+            self.start_oam_scan();
+        }
+    }
+
+    /// Returns and clears how many times the PPU entered HBlank on a visible line.
+    pub(crate) fn take_hblanks_started(&mut self) -> u32 {
+        std::mem::take(&mut self.hblanks_started)
+    }
+
+    /// Advances the PPU by `cycles` dots, returning the interrupts it requested as `IF` bits.
+    pub(crate) fn step(&mut self, cycles: u32) -> u8 {
+        let mut interrupts = 0;
+        if self.lcdc & LCDC_LCD_ENABLE == 0 {
+            return interrupts;
+        }
+
+        for _ in 0..cycles {
+            interrupts |= self.tick_dot();
+        }
+
+        interrupts
+    }
+
+    fn tick_dot(&mut self) -> u8 {
+        let mut interrupts = 0;
+        self.dot += 1;
+
+        match self.mode {
+            Mode::OamScan if self.dot == OAM_SCAN_DOTS => self.start_drawing(),
+            Mode::Drawing => {
+                let line_done = match self.line_renderer {
+                    Renderer::Scanline => self.dot == OAM_SCAN_DOTS + self.drawing_dots,
+                    Renderer::PixelFifo => self.tick_fifo(),
+                };
+
+                if line_done {
+                    if self.line_renderer == Renderer::Scanline {
+                        self.render_line();
+                    }
+                    self.mode = Mode::HBlank;
+                    self.hblanks_started += 1;
+                }
+            }
+            _ => {}
+        }
+
+        if self.line == LAST_LINE && self.dot == LAST_LINE_LY_RESET_DOT {
+            self.ly = 0;
+        }
+
+        if self.dot == DOTS_PER_LINE {
+            self.dot = 0;
+            self.line += 1;
+            if self.line == LINES_PER_FRAME {
+                self.line = 0;
+                self.window_y_triggered = false;
+                self.window_line = 0;
+            }
+            self.ly = self.line;
+
+            if self.line < VISIBLE_LINES {
+                self.start_oam_scan();
+            } else if self.line == VISIBLE_LINES {
+                self.mode = Mode::VBlank;
+                self.frame_count += 1;
+                interrupts |= Interrupt::VBlank.mask();
+            }
+        }
+
+        if self.update_stat_line() {
+            interrupts |= Interrupt::LcdStat.mask();
+        }
+
+        interrupts
+    }
+
+    fn start_oam_scan(&mut self) {
+        self.mode = Mode::OamScan;
+        if self.ly == self.wy {
+            self.window_y_triggered = true;
+        }
+    }
+
+    fn start_drawing(&mut self) {
+        self.scan_oam();
+        self.line_renderer = self.renderer;
+        match self.line_renderer {
+            Renderer::Scanline => self.drawing_dots = self.compute_drawing_dots(),
+            // The pixel FIFO takes as long as it takes.
+            Renderer::PixelFifo => self.fifo.reset(self.scx % 8),
+        }
+        self.mode = Mode::Drawing;
+    }
+
+    /// Returns how many dots mode 3 lasts on the current line: the pixels discarded for fine
+    /// scrolling and the stalls for fetching the window and sprites all lengthen it.
+    fn compute_drawing_dots(&self) -> u32 {
+        let fine_scroll = self.scx % 8;
+        let mut dots = MIN_DRAWING_DOTS + u32::from(fine_scroll);
+
+        if self.is_window_visible() {
+            dots += WINDOW_FETCH_DOTS;
+        }
+
+        if self.lcdc & LCDC_OBJ_ENABLE != 0 {
+            let mut waited_tiles = 0;
+            for sprite in &self.line_sprites {
+                if sprite.x < SCREEN_WIDTH as u8 + X_OFFSET {
+                    dots += Self::sprite_fetch_dots(sprite, fine_scroll, &mut waited_tiles);
+                }
+            }
+        }
+
+        dots
+    }
+
+    /// Returns how long fetching `sprite` stalls mode 3. Only the first sprite in each background
+    /// tile waits for the fetcher, for as long as the fetcher still needs to finish that tile;
+    /// `waited_tiles` keeps track of which tiles already had one.
+    fn sprite_fetch_dots(sprite: &Sprite, fine_scroll: u8, waited_tiles: &mut u32) -> u32 {
+        if sprite.x == 0 {
+            return SPRITE_FETCH_DOTS + MAX_SPRITE_WAIT_DOTS;
+        }
+
+        let position = u32::from(sprite.x) + u32::from(fine_scroll);
+        let tile = 1 << (position / 8);
+        if *waited_tiles & tile != 0 {
+            return SPRITE_FETCH_DOTS;
+        }
+        *waited_tiles |= tile;
+
+        SPRITE_FETCH_DOTS + MAX_SPRITE_WAIT_DOTS.saturating_sub(position % 8)
+    }
+
+    /// Re-evaluates the `STAT` interrupt line and returns whether it just went high. All sources
+    /// share the one line, so a source becoming active while another already holds it high
+    /// doesn't interrupt again.
+    fn update_stat_line(&mut self) -> bool {
+        let mode_source = match self.mode {
+            Mode::HBlank => STAT_HBLANK_INTERRUPT,
+            // Line 144 starts with an OAM scan that's cut short, which still raises its source.
+            Mode::VBlank if self.line == VISIBLE_LINES && self.dot == 0 => {
+                STAT_VBLANK_INTERRUPT | STAT_OAM_INTERRUPT
+            }
+            Mode::VBlank => STAT_VBLANK_INTERRUPT,
+            Mode::OamScan => STAT_OAM_INTERRUPT,
+            Mode::Drawing => 0,
+        };
+        let line = self.stat & mode_source != 0
+            || (self.stat & STAT_LYC_INTERRUPT != 0 && self.ly == self.lyc);
+
+        let went_high = line && !self.stat_line;
+        self.stat_line = line;
+
+        went_high
+    }
+
+    fn is_window_visible(&self) -> bool {
+        self.lcdc & LCDC_WINDOW_ENABLE != 0
+            && self.window_y_triggered
+            && self.wx < SCREEN_WIDTH as u8 + WINDOW_X_OFFSET
+    }
+
+    const fn sprite_height(&self) -> u8 {
+        if self.lcdc & LCDC_OBJ_SIZE != 0 {
+            16
+        } else {
+            8
+        }
+    }
+
+    /// Selects the first ten sprites in OAM that overlap the current line, whatever their X
+    /// position, and orders them by drawing priority.
+    fn scan_oam(&mut self) {
+        let height = self.sprite_height();
+        let line = self.ly + Y_OFFSET;
+
+        self.line_sprites.clear();
+        for index in 0..SPRITE_COUNT {
+            let sprite = Sprite::from_oam(&self.oam, index);
+            if (sprite.y..sprite.y.saturating_add(height)).contains(&line) {
+                self.line_sprites.push(sprite);
+                if self.line_sprites.len() == MAX_SPRITES_PER_LINE {
+                    break;
+                }
+            }
+        }
+
+        // On the DMG the leftmost sprite wins, with ties going to the lower OAM index. The sort
+        // is stable, so the scan order settles ties. In CGB mode the OAM index alone decides.
+        if !self.cgb_mode {
+            self.line_sprites.sort_by_key(|sprite| sprite.x);
+        }
+    }
+
+    /// Returns `sprite`'s pixels on the current line, left to right.
+    fn sprite_pixels(&self, sprite: &Sprite) -> [ObjPixel; 8] {
+        // The OBJ size can change after the OAM scan picked the sprite, leaving its row out of
+        // range for the current height. The row wraps within the sprite instead.
+        let height = self.sprite_height();
+        let mut row = (self.ly + Y_OFFSET - sprite.y) & (height - 1);
+        if sprite.is_y_flipped() {
+            row = height - 1 - row;
+        }
+
+        // 8x16 sprites ignore the low bit of the tile index and cover two consecutive tiles.
+        let tile = if height == 16 {
+            sprite.tile & 0xFE
+        } else {
+            sprite.tile
+        };
+        let (bank, palette) = if self.cgb_mode {
+            (sprite.vram_bank(), sprite.cgb_palette())
+        } else {
+            (0, u8::from(sprite.uses_obp1()))
+        };
+        let offset = usize::from(bank) * VRAM_BANK_SIZE
+            + usize::from(tile) * BYTES_PER_TILE
+            + usize::from(row) * 2;
+        let (low, high) = (self.vram[offset], self.vram[offset + 1]);
+
+        let mut pixels = [ObjPixel::default(); 8];
+        for (column, pixel) in (0..8).zip(pixels.iter_mut()) {
+            let bit = if sprite.is_x_flipped() {
+                column
+            } else {
+                7 - column
+            };
+
+            *pixel = ObjPixel {
+                color_id: Self::color_id(low, high, bit),
+                palette,
+                behind_background: sprite.is_behind_background(),
+                oam_index: sprite.oam_index,
+            };
+        }
+
+        pixels
+    }
+
+    /// Returns the tile index and CGB attributes of the entry at (`column`, `row`) of a tile map.
+    /// The attributes are always 0 outside CGB mode.
+    fn tile_map_entry(&self, high_map: bool, column: usize, row: usize) -> (u8, u8) {
+        let map = if high_map { TILE_MAP_1 } else { TILE_MAP_0 };
+        let offset = map + (row % TILE_MAP_WIDTH) * TILE_MAP_WIDTH + column % TILE_MAP_WIDTH;
+        let attributes = if self.cgb_mode {
+            self.vram[VRAM_BANK_SIZE + offset]
+        } else {
+            0
+        };
+
+        (self.vram[offset], attributes)
+    }
+
+    /// Returns the low and high bit planes of line `y` of a background or window tile.
+    fn bg_tile_row(&self, tile_index: u8, attributes: u8, y: u8) -> (u8, u8) {
+        let y = if attributes & BG_ATTRIBUTE_Y_FLIP != 0 {
+            7 - y % 8
+        } else {
+            y % 8
+        };
+        let bank = if attributes & BG_ATTRIBUTE_VRAM_BANK != 0 {
+            VRAM_BANK_SIZE
+        } else {
+            0
+        };
+        let offset = bank + self.tile_data_offset(tile_index) + usize::from(y) * 2;
+
+        (self.vram[offset], self.vram[offset + 1])
+    }
+
+    /// Returns pixel `column` of a background or window tile line.
+    const fn bg_pixel(low: u8, high: u8, attributes: u8, column: u8) -> BgPixel {
+        let bit = if attributes & BG_ATTRIBUTE_X_FLIP != 0 {
+            column
+        } else {
+            7 - column
+        };
+
+        BgPixel {
+            color_id: Self::color_id(low, high, bit),
+            palette: attributes & BG_ATTRIBUTE_PALETTE,
+            has_priority: attributes & BG_ATTRIBUTE_PRIORITY != 0,
+        }
+    }
+
+    /// Returns where a background or window tile's data starts in VRAM, which depends on the
+    /// addressing mode LCDC bit 4 selects.
+    fn tile_data_offset(&self, tile_index: u8) -> usize {
+        if self.lcdc & LCDC_TILE_DATA != 0 {
+            usize::from(tile_index) * BYTES_PER_TILE
+        } else {
+            let offset = isize::from(tile_index as i8) * BYTES_PER_TILE as isize;
+
+            SIGNED_TILE_DATA_BASE.wrapping_add_signed(offset)
+        }
+    }
+
+    /// Returns the 2-bit color ID at `bit` of a tile line's two bit planes.
+    const fn color_id(low: u8, high: u8, bit: u8) -> u8 {
+        ((high >> bit) & 0b1) << 1 | (low >> bit) & 0b1
+    }
+
+    /// Returns the color of a screen pixel, given the background pixel and the highest priority
+    /// sprite pixel over it.
+    fn mix_pixel(&self, bg: BgPixel, object: ObjPixel) -> Color {
+        let bg_enabled = self.lcdc & LCDC_BG_WINDOW_ENABLE != 0;
+        let object_visible = object.color_id != 0 && self.lcdc & LCDC_OBJ_ENABLE != 0;
+
+        if self.cgb_mode {
+            // In CGB mode, clearing LCDC bit 0 doesn't hide the background but takes away all its
+            // priority over sprites.
+            let bg_wins =
+                bg_enabled && bg.color_id != 0 && (bg.has_priority || object.behind_background);
+
+            return if object_visible && !bg_wins {
+                self.obj_palettes.color(object.palette, object.color_id)
+            } else {
+                self.bg_palettes.color(bg.palette, bg.color_id)
+            };
+        }
+
+        // On the DMG, clearing LCDC bit 0 blanks both the background and the window.
+        let bg_color_id = if bg_enabled { bg.color_id } else { 0 };
+        if object_visible && !(object.behind_background && bg_color_id != 0) {
+            let palette = if object.palette == 0 {
+                self.obp0
+            } else {
+                self.obp1
+            };
+
+            return self.dmg_color(
+                &self.obj_palettes,
+                object.palette,
+                Self::apply_palette(palette, object.color_id),
+            );
+        }
+
+        let shade = if bg_enabled {
+            Self::apply_palette(self.bgp, bg_color_id)
+        } else {
+            0
+        };
+
+        self.dmg_color(&self.bg_palettes, 0, shade)
+    }
+
+    /// Returns the color of a DMG `shade`, which CGB hardware looks up in its palette RAM.
+    fn dmg_color(&self, palettes: &PaletteRam, palette: u8, shade: u8) -> Color {
+        if self.cgb_hardware {
+            palettes.color(palette, shade)
+        } else {
+            DMG_SHADES[usize::from(shade)]
+        }
+    }
+
+    const fn apply_palette(palette: u8, color_id: u8) -> u8 {
+        (palette >> (color_id * 2)) & 0b11
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BGP: u8 = 0xE4;
+    const INVERTED_BGP: u8 = 0x1B;
+
+    /// Returns a PPU with tile data, both tile maps and OAM filled with noise, about to draw a
+    /// frame with the background, the window and sprites all on.
+    fn ppu_with_scene(renderer: Renderer, bgp: u8, scx: u8) -> Ppu {
+        let mut ppu = Ppu::new(Model::Dmg);
+        ppu.set_renderer(renderer);
+
+        let mut seed = 0x1234_5678_u32;
+        let mut noise = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) as u8
+        };
+        for address in 0x8000..0xA000 {
+            ppu.write_vram(address, noise());
+        }
+        for sprite in 0..10 {
+            let address = OAM_START + sprite * 4;
+            ppu.write_oam(address, 16 + (noise() % 144));
+            ppu.write_oam(address + 1, 8 + (noise() % 160));
+            ppu.write_oam(address + 2, noise());
+            ppu.write_oam(address + 3, noise() & 0b1110_0000);
+        }
+
+        ppu.write_register(SCY_ADDRESS, 5);
+        ppu.write_register(SCX_ADDRESS, scx);
+        ppu.write_register(WY_ADDRESS, 40);
+        ppu.write_register(WX_ADDRESS, 50);
+        ppu.write_register(BGP_ADDRESS, bgp);
+        ppu.write_register(OBP0_ADDRESS, 0xD2);
+        ppu.write_register(OBP1_ADDRESS, 0x39);
+        ppu.write_register(LCDC_ADDRESS, 0xF3);
+
+        ppu
+    }
+
+    /// Steps `ppu` until it reaches `dot` of `line`.
+    fn step_until(ppu: &mut Ppu, line: u8, dot: u32) {
+        while (ppu.line, ppu.dot) != (line, dot) {
+            ppu.step(1);
+        }
+    }
+
+    /// Steps `ppu` through the rest of the frame being drawn.
+    fn finish_frame(ppu: &mut Ppu) {
+        let frame_count = ppu.frame_count;
+        while ppu.frame_count == frame_count {
+            ppu.step(1);
+        }
+    }
+
+    fn screen_line(ppu: &Ppu, line: usize) -> &[Color] {
+        &ppu.framebuffer()[line * SCREEN_WIDTH..(line + 1) * SCREEN_WIDTH]
+    }
+
+    #[test]
+    fn fifo_matches_scanline_for_static_frame() {
+        let mut scanline = ppu_with_scene(Renderer::Scanline, BGP, 3);
+        let mut fifo = ppu_with_scene(Renderer::PixelFifo, BGP, 3);
+        finish_frame(&mut scanline);
+        finish_frame(&mut fifo);
+
+        for line in 0..SCREEN_HEIGHT {
+            assert_eq!(
+                screen_line(&fifo, line),
+                screen_line(&scanline, line),
+                "line {line}"
+            );
+        }
+    }
+
+    #[test]
+    fn fifo_shows_mid_line_bgp_change() {
+        let mut before = ppu_with_scene(Renderer::Scanline, BGP, 0);
+        let mut after = ppu_with_scene(Renderer::Scanline, INVERTED_BGP, 0);
+        finish_frame(&mut before);
+        finish_frame(&mut after);
+
+        for renderer in [Renderer::Scanline, Renderer::PixelFifo] {
+            let mut ppu = ppu_with_scene(renderer, BGP, 0);
+            step_until(&mut ppu, 10, OAM_SCAN_DOTS + 100);
+            ppu.write_register(BGP_ADDRESS, INVERTED_BGP);
+            finish_frame(&mut ppu);
+
+            let line = screen_line(&ppu, 10);
+            let (left, right) = (0..40, 120..SCREEN_WIDTH);
+            assert_eq!(line[right.clone()], screen_line(&after, 10)[right]);
+            // The scanline renderer draws the whole line with the last value.
+            let expected = if renderer == Renderer::PixelFifo {
+                &before
+            } else {
+                &after
+            };
+            assert_eq!(line[left.clone()], screen_line(expected, 10)[left]);
+        }
+    }
+
+    #[test]
+    fn fifo_shows_mid_line_scx_change() {
+        let mut before = ppu_with_scene(Renderer::Scanline, BGP, 0);
+        let mut after = ppu_with_scene(Renderer::Scanline, BGP, 16);
+        finish_frame(&mut before);
+        finish_frame(&mut after);
+
+        let mut ppu = ppu_with_scene(Renderer::PixelFifo, BGP, 0);
+        step_until(&mut ppu, 10, OAM_SCAN_DOTS + 100);
+        ppu.write_register(SCX_ADDRESS, 16);
+        finish_frame(&mut ppu);
+
+        // Line 10 is above the window, so the background fills it.
+        let line = screen_line(&ppu, 10);
+        assert_eq!(line[..40], screen_line(&before, 10)[..40]);
+        assert_eq!(line[120..], screen_line(&after, 10)[120..]);
+    }
+
+    fn enabled_ppu() -> Ppu {
+        let mut ppu = Ppu::new(Model::Dmg);
+        ppu.write_register(LCDC_ADDRESS, 0x91);
+
+        ppu
+    }
+
+    /// Steps `ppu` a dot at a time until it reaches `dot` of `line`, returning how many STAT
+    /// interrupts it requested on the way.
+    fn stat_interrupts_until(ppu: &mut Ppu, line: u8, dot: u32) -> usize {
+        let mut count = 0;
+        while (ppu.line, ppu.dot) != (line, dot) {
+            if ppu.step(1) & Interrupt::LcdStat.mask() != 0 {
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn vblank_start_raises_oam_source() {
+        let mut ppu = enabled_ppu();
+        ppu.write_register(STAT_ADDRESS, STAT_OAM_INTERRUPT);
+        stat_interrupts_until(&mut ppu, 143, 100);
+
+        assert_eq!(stat_interrupts_until(&mut ppu, 144, 1), 1);
+        assert_eq!(stat_interrupts_until(&mut ppu, 0, 1), 1);
+
+        // With the VBlank source on as well, the line only rises once.
+        ppu.write_register(STAT_ADDRESS, STAT_OAM_INTERRUPT | STAT_VBLANK_INTERRUPT);
+        stat_interrupts_until(&mut ppu, 143, 100);
+        assert_eq!(stat_interrupts_until(&mut ppu, 145, 0), 1);
+    }
+
+    #[test]
+    fn stat_line_staying_high_blocks_interrupts() {
+        let mut ppu = enabled_ppu();
+        ppu.write_register(STAT_ADDRESS, STAT_HBLANK_INTERRUPT | STAT_LYC_INTERRUPT);
+        ppu.write_register(LYC_ADDRESS, 1);
+        stat_interrupts_until(&mut ppu, 0, 100);
+
+        // Line 0's HBlank raises the line, which LY=LYC then holds high through all of line 1.
+        assert_eq!(stat_interrupts_until(&mut ppu, 2, 0), 1);
+        assert_eq!(stat_interrupts_until(&mut ppu, 3, 0), 1);
+    }
+
+    #[test]
+    fn ly_reads_0_early_on_line_153() {
+        let mut ppu = enabled_ppu();
+        ppu.write_register(STAT_ADDRESS, STAT_LYC_INTERRUPT);
+        ppu.write_register(LYC_ADDRESS, 153);
+        stat_interrupts_until(&mut ppu, 152, 100);
+
+        assert_eq!(stat_interrupts_until(&mut ppu, 153, 1), 1);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 153);
+        stat_interrupts_until(&mut ppu, 153, LAST_LINE_LY_RESET_DOT);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 0);
+
+        // LYC=0 already matches on line 153, and stays matched into line 0.
+        ppu.write_register(LYC_ADDRESS, 0);
+        stat_interrupts_until(&mut ppu, 152, 100);
+        assert_eq!(
+            stat_interrupts_until(&mut ppu, 153, LAST_LINE_LY_RESET_DOT + 1),
+            1
+        );
+        assert_eq!(stat_interrupts_until(&mut ppu, 1, 0), 0);
+    }
+}
@@ -0,0 +1,84 @@
+const PALETTE_RAM_SIZE: usize = 64;
+const COLORS_PER_PALETTE: usize = 4;
+
+const AUTO_INCREMENT_FLAG: u8 = 1 << 7;
+const INDEX_MASK: u8 = 0b0011_1111;
+/// Bit 6 of the specification register is unused and reads as 1.
+const SPECIFICATION_UNUSED_BITS: u8 = 0b0100_0000;
+
+/// A 15-bit color, with red in the low five bits and blue in the high ones.
+pub type Color = u16;
+
+pub const WHITE: Color = 0x7FFF;
+
+/// The shades of the DMG, from white to black, for models without color palettes.
+pub const DMG_SHADES: [Color; 4] = [WHITE, 0x5294, 0x294A, 0x0000];
+
+/// One of the CGB's two banks of palette RAM, holding eight palettes of four colors each. The
+/// CPU reaches it through a specification register (`BCPS`/`OCPS`) selecting a byte and a data
+/// register (`BCPD`/`OCPD`) reading or writing it.
+#[derive(Debug, Clone)]
+pub struct PaletteRam {
+    data: [u8; PALETTE_RAM_SIZE],
+    index: u8,
+    auto_increment: bool,
+}
+
+impl PaletteRam {
+    pub const fn new() -> Self {
+        Self {
+            data: [0xFF; PALETTE_RAM_SIZE],
+            index: 0,
+            auto_increment: false,
+        }
+    }
+
+    /// Sets every color of every palette to `color`.
+    pub fn fill(&mut self, color: Color) {
+        for pair in self.data.chunks_exact_mut(2) {
+            pair.copy_from_slice(&color.to_le_bytes());
+        }
+    }
+
+    /// Sets the colors of palette `palette`.
+    pub fn set_palette(&mut self, palette: usize, colors: [Color; COLORS_PER_PALETTE]) {
+        for (color_id, color) in colors.into_iter().enumerate() {
+            let offset = (palette * COLORS_PER_PALETTE + color_id) * 2;
+            self.data[offset..offset + 2].copy_from_slice(&color.to_le_bytes());
+        }
+    }
+
+    pub const fn read_specification(&self) -> u8 {
+        let auto_increment = if self.auto_increment {
+            AUTO_INCREMENT_FLAG
+        } else {
+            0
+        };
+
+        auto_increment | SPECIFICATION_UNUSED_BITS | self.index
+    }
+
+    pub fn write_specification(&mut self, byte: u8) {
+        self.index = byte & INDEX_MASK;
+        self.auto_increment = byte & AUTO_INCREMENT_FLAG != 0;
+    }
+
+    pub const fn read_data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    /// Writes the selected byte, moving on to the next one if auto-increment is on.
+    pub fn write_data(&mut self, byte: u8) {
+        self.data[usize::from(self.index)] = byte;
+        if self.auto_increment {
+            self.index = (self.index + 1) & INDEX_MASK;
+        }
+    }
+
+    /// Returns color `color_id` of palette `palette`.
+    pub fn color(&self, palette: u8, color_id: u8) -> Color {
+        let offset = (usize::from(palette) * COLORS_PER_PALETTE + usize::from(color_id)) * 2;
+
+        Color::from_le_bytes([self.data[offset], self.data[offset + 1]]) & 0x7FFF
+    }
+}
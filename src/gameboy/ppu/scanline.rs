@@ -0,0 +1,92 @@
+use super::{
+    BgPixel, ObjPixel, Ppu, LCDC_BG_TILE_MAP, LCDC_BG_WINDOW_ENABLE, LCDC_WINDOW_TILE_MAP,
+    MAX_SPRITES_PER_LINE, SCREEN_WIDTH, WINDOW_X_OFFSET, X_OFFSET,
+};
+
+/// The scanline renderer draws each line in one go once mode 3 ends. It is fast, but misses
+/// register writes made while the line is being drawn.
+impl Ppu {
+    /// Draws the background, window and sprites for the current line.
+    pub(super) fn render_line(&mut self) {
+        let mut bg_pixels = [BgPixel::default(); SCREEN_WIDTH];
+        // Outside CGB mode, clearing LCDC bit 0 blanks the background and window, so there is
+        // nothing to fetch.
+        if self.cgb_mode || self.lcdc & LCDC_BG_WINDOW_ENABLE != 0 {
+            self.render_background(&mut bg_pixels);
+        }
+
+        let object_pixels = self.render_sprites();
+
+        let start = usize::from(self.ly) * SCREEN_WIDTH;
+        for (x, (&bg, &object)) in bg_pixels.iter().zip(&object_pixels).enumerate() {
+            self.framebuffer[start + x] = self.mix_pixel(bg, object);
+        }
+    }
+
+    /// Fills `pixels` with the background and window for the current line.
+    fn render_background(&mut self, pixels: &mut [BgPixel; SCREEN_WIDTH]) {
+        let window_visible = self.is_window_visible();
+        let mut window_drawn = false;
+
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            let x = x as u8;
+            *pixel = if window_visible && x + WINDOW_X_OFFSET >= self.wx {
+                window_drawn = true;
+
+                self.tile_map_pixel(
+                    self.lcdc & LCDC_WINDOW_TILE_MAP != 0,
+                    x + WINDOW_X_OFFSET - self.wx,
+                    self.window_line,
+                )
+            } else {
+                self.tile_map_pixel(
+                    self.lcdc & LCDC_BG_TILE_MAP != 0,
+                    self.scx.wrapping_add(x),
+                    self.scy.wrapping_add(self.ly),
+                )
+            };
+        }
+
+        if window_drawn {
+            self.window_line += 1;
+        }
+    }
+
+    /// Returns the highest priority sprite pixel in each column of the current line.
+    fn render_sprites(&self) -> [ObjPixel; SCREEN_WIDTH] {
+        let mut rows = [[ObjPixel::default(); 8]; MAX_SPRITES_PER_LINE];
+        for (row, sprite) in rows.iter_mut().zip(&self.line_sprites) {
+            *row = self.sprite_pixels(sprite);
+        }
+
+        let mut pixels = [ObjPixel::default(); SCREEN_WIDTH];
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            // A transparent pixel lets lower priority sprites show through, but an opaque one
+            // decides the background priority on its own.
+            let winner = self
+                .line_sprites
+                .iter()
+                .zip(&rows)
+                .find_map(|(sprite, row)| {
+                    let column = (x + usize::from(X_OFFSET)).checked_sub(usize::from(sprite.x))?;
+
+                    row.get(column).filter(|pixel| pixel.color_id != 0)
+                });
+
+            if let Some(&winner) = winner {
+                *pixel = winner;
+            }
+        }
+
+        pixels
+    }
+
+    /// Returns pixel (`x`, `y`) of the 256x256 image a tile map describes.
+    fn tile_map_pixel(&self, high_map: bool, x: u8, y: u8) -> BgPixel {
+        let (tile_index, attributes) =
+            self.tile_map_entry(high_map, usize::from(x / 8), usize::from(y / 8));
+        let (low, high) = self.bg_tile_row(tile_index, attributes, y);
+
+        Self::bg_pixel(low, high, attributes, x % 8)
+    }
+}
@@ -0,0 +1,64 @@
+const PRIORITY_FLAG: u8 = 1 << 7;
+const Y_FLIP_FLAG: u8 = 1 << 6;
+const X_FLIP_FLAG: u8 = 1 << 5;
+const DMG_PALETTE_FLAG: u8 = 1 << 4;
+const VRAM_BANK_FLAG: u8 = 1 << 3;
+const CGB_PALETTE_MASK: u8 = 0b0000_0111;
+
+pub const BYTES_PER_SPRITE: usize = 4;
+pub const SPRITE_COUNT: usize = 40;
+/// OAM positions are offset so sprites can sit partially off the top and left edges.
+pub const Y_OFFSET: u8 = 16;
+pub const X_OFFSET: u8 = 8;
+
+/// An OAM entry.
+#[derive(Debug, Clone, Copy)]
+pub struct Sprite {
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: u8,
+    pub oam_index: u8,
+}
+
+impl Sprite {
+    /// Decodes the entry at `oam_index` from OAM.
+    pub fn from_oam(oam: &[u8], oam_index: usize) -> Self {
+        let entry = &oam[oam_index * BYTES_PER_SPRITE..][..BYTES_PER_SPRITE];
+
+        Self {
+            y: entry[0],
+            x: entry[1],
+            tile: entry[2],
+            attributes: entry[3],
+            oam_index: oam_index as u8,
+        }
+    }
+
+    /// Returns whether the background and window's non-zero colors are drawn over the sprite.
+    pub const fn is_behind_background(&self) -> bool {
+        self.attributes & PRIORITY_FLAG != 0
+    }
+
+    pub const fn is_y_flipped(&self) -> bool {
+        self.attributes & Y_FLIP_FLAG != 0
+    }
+
+    pub const fn is_x_flipped(&self) -> bool {
+        self.attributes & X_FLIP_FLAG != 0
+    }
+
+    /// Returns whether the sprite uses `OBP1` rather than `OBP0` on the DMG.
+    pub const fn uses_obp1(&self) -> bool {
+        self.attributes & DMG_PALETTE_FLAG != 0
+    }
+
+    /// Returns the VRAM bank holding the sprite's tile in CGB mode.
+    pub const fn vram_bank(&self) -> u8 {
+        (self.attributes & VRAM_BANK_FLAG) >> 3
+    }
+
+    pub const fn cgb_palette(&self) -> u8 {
+        self.attributes & CGB_PALETTE_MASK
+    }
+}
@@ -0,0 +1,246 @@
+use super::{interrupt::Interrupt, model::Model};
+
+pub(crate) const DIV_ADDRESS: u16 = 0xFF04;
+pub(crate) const TIMA_ADDRESS: u16 = 0xFF05;
+pub(crate) const TMA_ADDRESS: u16 = 0xFF06;
+pub(crate) const TAC_ADDRESS: u16 = 0xFF07;
+
+const TAC_ENABLE: u8 = 1 << 2;
+const TAC_CLOCK_SELECT_MASK: u8 = 0b011;
+/// The unused upper bits of `TAC` always read as 1.
+const TAC_UNUSED_BITS: u8 = 0b1111_1000;
+
+/// How far the system counter advances every M-cycle.
+const COUNTER_STEP_PER_M_CYCLE: u16 = 4;
+
+/// The DIV register and the programmable timer, both driven by a 16-bit system counter that
+/// advances with the CPU's clock.
+#[derive(Debug, Clone, Default)]
+pub struct Timer {
+    /// The system counter, whose upper byte is `DIV`.
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    /// Whether `TIMA` overflowed during the last M-cycle, so it reads 0 until it's reloaded.
+    overflowed: bool,
+    /// Whether `TIMA` was reloaded from `TMA` during the last M-cycle, which makes writes to
+    /// `TIMA` lose against the reload and writes to `TMA` go through to `TIMA` as well.
+    reloaded: bool,
+}
+
+impl Timer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts the system counter where the boot ROM leaves it, which depends on how long the boot
+    /// ROM runs.
+    pub(crate) fn apply_post_boot_state(&mut self, model: Model) {
+        self.counter = match model {
+            Model::Dmg0 => 0x1800,
+            Model::Dmg | Model::Mgb | Model::Sgb | Model::Sgb2 => 0xABCC,
+            Model::Cgb | Model::Agb => 0x1EA0,
+        };
+    }
+
+    pub(crate) const fn read_register(&self, address: u16) -> u8 {
+        match address {
+            DIV_ADDRESS => self.counter.to_be_bytes()[0],
+            TIMA_ADDRESS => self.tima,
+            TMA_ADDRESS => self.tma,
+            TAC_ADDRESS => self.tac | TAC_UNUSED_BITS,
+            _ => 0xFF,
+        }
+    }
+
+    pub(crate) fn write_register(&mut self, address: u16, byte: u8) {
+        match address {
+            DIV_ADDRESS => self.reset_divider(),
+            // Writing TIMA cancels a pending reload and its interrupt, but loses against a reload
+            // that's happening right now.
+            TIMA_ADDRESS if !self.reloaded => {
+                self.tima = byte;
+                self.overflowed = false;
+            }
+            TMA_ADDRESS => {
+                self.tma = byte;
+                if self.reloaded {
+                    self.tima = byte;
+                }
+            }
+            // Switching to another bit, or disabling the timer, can look like a falling edge too.
+            TAC_ADDRESS => {
+                let was_high = self.timer_input();
+                self.tac = byte & !TAC_UNUSED_BITS;
+                self.tick_on_falling_edge(was_high);
+            }
+            _ => {}
+        }
+    }
+
+    /// Resets the system counter, as writing to `DIV` does. This can make the selected bit fall,
+    /// which ticks TIMA.
+    pub(crate) fn reset_divider(&mut self) {
+        let was_high = self.timer_input();
+        self.counter = 0;
+        self.tick_on_falling_edge(was_high);
+    }
+
+    /// Advances the timer by `m_cycles` M-cycles, returning the interrupts it requested as `IF`
+    /// bits.
+    pub(crate) fn step(&mut self, m_cycles: u32) -> u8 {
+        let mut interrupts = 0;
+        for _ in 0..m_cycles {
+            self.reloaded = false;
+            if self.overflowed {
+                self.overflowed = false;
+                self.reloaded = true;
+                self.tima = self.tma;
+                interrupts |= Interrupt::Timer.mask();
+            }
+
+            let was_high = self.timer_input();
+            self.counter = self.counter.wrapping_add(COUNTER_STEP_PER_M_CYCLE);
+            self.tick_on_falling_edge(was_high);
+        }
+
+        interrupts
+    }
+
+    /// Returns the counter bit `TAC` selects, ANDed with its enable bit. TIMA ticks whenever
+    /// this falls.
+    const fn timer_input(&self) -> bool {
+        let bit = match self.tac & TAC_CLOCK_SELECT_MASK {
+            0b00 => 9,
+            0b01 => 3,
+            0b10 => 5,
+            _ => 7,
+        };
+
+        self.tac & TAC_ENABLE != 0 && self.counter & (1 << bit) != 0
+    }
+
+    fn tick_on_falling_edge(&mut self, was_high: bool) {
+        if !was_high || self.timer_input() {
+            return;
+        }
+
+        let (tima, overflowed) = self.tima.overflowing_add(1);
+        self.tima = tima;
+        // The reload from TMA only happens an M-cycle later.
+        self.overflowed |= overflowed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// TAC enabled with TIMA ticking on bit 3, every 4 M-cycles.
+    const TAC_BIT_3: u8 = TAC_ENABLE | 0b01;
+
+    /// Returns a timer that overflowed on the M-cycle it just stepped through, with the reload
+    /// from a `TMA` of 0x42 still pending.
+    fn overflowed_timer() -> Timer {
+        let mut timer = Timer::new();
+        timer.write_register(TMA_ADDRESS, 0x42);
+        timer.write_register(TIMA_ADDRESS, 0xFF);
+        timer.write_register(TAC_ADDRESS, TAC_BIT_3);
+        assert_eq!(timer.step(4), 0);
+
+        timer
+    }
+
+    #[test]
+    fn tima_ticks_on_falling_edge() {
+        let mut timer = Timer::new();
+        timer.write_register(TAC_ADDRESS, TAC_BIT_3);
+
+        timer.step(3);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0);
+        timer.step(1);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 1);
+    }
+
+    #[test]
+    fn div_is_upper_counter_byte_and_resets_on_write() {
+        let mut timer = Timer::new();
+        timer.step(64);
+        assert_eq!(timer.read_register(DIV_ADDRESS), 1);
+
+        timer.write_register(DIV_ADDRESS, 0xAB);
+        assert_eq!(timer.read_register(DIV_ADDRESS), 0);
+    }
+
+    #[test]
+    fn div_write_ticks_tima_when_selected_bit_is_high() {
+        let mut timer = Timer::new();
+        timer.write_register(TAC_ADDRESS, TAC_BIT_3);
+        timer.step(2);
+
+        timer.write_register(DIV_ADDRESS, 0);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 1);
+
+        // With the bit low, resetting the counter doesn't tick TIMA.
+        timer.write_register(DIV_ADDRESS, 0);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 1);
+    }
+
+    #[test]
+    fn tac_write_ticks_tima_when_selected_bit_falls() {
+        let mut timer = Timer::new();
+        timer.write_register(TAC_ADDRESS, TAC_BIT_3);
+        timer.step(2);
+
+        timer.write_register(TAC_ADDRESS, 0);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 1);
+
+        // Switching from bit 3 to bit 9, which is still low, looks like a falling edge too.
+        timer.write_register(TAC_ADDRESS, TAC_BIT_3);
+        timer.write_register(TAC_ADDRESS, TAC_ENABLE);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 2);
+    }
+
+    #[test]
+    fn overflow_reloads_one_m_cycle_late() {
+        let mut timer = overflowed_timer();
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0);
+
+        assert_eq!(timer.step(1), Interrupt::Timer.mask());
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0x42);
+    }
+
+    #[test]
+    fn tima_write_cancels_pending_reload() {
+        let mut timer = overflowed_timer();
+        timer.write_register(TIMA_ADDRESS, 0x10);
+
+        assert_eq!(timer.step(1), 0);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0x10);
+    }
+
+    #[test]
+    fn writes_during_reload_cycle() {
+        let mut timer = overflowed_timer();
+        timer.step(1);
+
+        timer.write_register(TIMA_ADDRESS, 0x10);
+        assert_eq!(
+            timer.read_register(TIMA_ADDRESS),
+            0x42,
+            "the reload wins over TIMA writes"
+        );
+
+        timer.write_register(TMA_ADDRESS, 0x77);
+        assert_eq!(
+            timer.read_register(TIMA_ADDRESS),
+            0x77,
+            "TMA writes go through to TIMA"
+        );
+
+        timer.step(1);
+        timer.write_register(TMA_ADDRESS, 0x88);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0x77);
+    }
+}
@@ -1,5 +1,141 @@
-mod gameboy;
+use std::{
+    env, process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use gameboy_rs::gameboy::{
+    boot::BootRom,
+    cartridge::{save::BatterySave, Cartridge, CartridgeError},
+    cpu::Cpu,
+    memory::MemoryBus,
+    model::Model,
+    ppu::Renderer,
+};
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+/// How often battery-backed RAM is written back to disk, in T-cycles of emulated time.
+const SAVE_FLUSH_INTERVAL: u32 = 4_194_304;
 
 fn main() {
-    println!("Hello, world!");
+    let mut args = env::args().skip(1).peekable();
+    let mut forced_model = None;
+    let mut renderer = Renderer::default();
+    while let Some(option) = args.next_if(|arg| arg.starts_with("--")) {
+        match option.as_str() {
+            "--model" => {
+                let name = args.next().unwrap_or_default();
+                forced_model = Some(name.parse::<Model>().unwrap_or_else(|error| {
+                    eprintln!("{error}");
+                    process::exit(1);
+                }));
+            }
+            "--pixel-fifo" => renderer = Renderer::PixelFifo,
+            _ => {
+                eprintln!("Unknown option {option}!");
+                process::exit(1);
+            }
+        }
+    }
+    let Some(path) = args.next() else {
+        eprintln!("Usage: gameboy_rs [--model MODEL] [--pixel-fifo] <ROM> [BOOT ROM]");
+        process::exit(1);
+    };
+    let boot_rom_path = args.next();
+
+    let cartridge = Cartridge::from_path(&path).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        process::exit(1);
+    });
+    match cartridge.verify_checksums() {
+        Ok(()) => {}
+        // Only the boot ROM's header check stops real hardware, so a bad global checksum is
+        // just worth a warning.
+        Err(error @ CartridgeError::GlobalChecksumMismatch { .. }) => {
+            eprintln!("Warning: {error}");
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+    }
+
+    let header = cartridge.header();
+    println!(
+        "Loaded \"{}\" (type 0x{:02X}, {} KiB ROM, {} KiB RAM, licensee {}, version {}).",
+        header.title,
+        header.cartridge_type.code,
+        header.rom_size / 1024,
+        header.ram_size / 1024,
+        header.licensee,
+        header.version,
+    );
+
+    let mut battery_save = cartridge.battery_save();
+    let model = forced_model.unwrap_or_else(|| Model::for_cartridge(header));
+    println!("Emulating model {model}.");
+    let mut bus = cartridge.into_memory_bus(model).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        process::exit(1);
+    });
+    bus.ppu_mut().set_renderer(renderer);
+    if let Some(boot_rom_path) = boot_rom_path {
+        let boot_rom = BootRom::from_path(model, boot_rom_path).unwrap_or_else(|error| {
+            eprintln!("{error}");
+            process::exit(1);
+        });
+        bus.map_boot_rom(boot_rom);
+    }
+    let mut cpu = Cpu::new(bus);
+
+    if let Some(save) = &mut battery_save {
+        if let Err(error) = save.load(cpu.bus_mut()) {
+            eprintln!("Failed to load {}: {error}", save.path().display());
+            process::exit(1);
+        }
+    }
+
+    // Ctrl-C and SIGTERM only raise a flag, so the save still gets written on the way out.
+    let should_quit = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        if let Err(error) = signal_hook::flag::register(signal, Arc::clone(&should_quit)) {
+            eprintln!("Failed to install the signal handler: {error}");
+            process::exit(1);
+        }
+    }
+
+    let mut unsaved_cycles = 0;
+    let result = loop {
+        if should_quit.load(Ordering::Relaxed) {
+            break Ok(());
+        }
+
+        match cpu.run_frame() {
+            Ok(cycles) => unsaved_cycles += cycles,
+            Err(error) => break Err(error),
+        }
+
+        if unsaved_cycles >= SAVE_FLUSH_INTERVAL {
+            unsaved_cycles = 0;
+            flush_save(battery_save.as_mut(), cpu.bus());
+        }
+    };
+    flush_save(battery_save.as_mut(), cpu.bus());
+
+    if let Err(error) = result {
+        eprintln!("{error}");
+        process::exit(1);
+    }
+}
+
+/// Writes the cartridge RAM on `bus` to `save`, if the cartridge has one, reporting failures
+/// without stopping.
+fn flush_save(save: Option<&mut BatterySave>, bus: &MemoryBus) {
+    if let Some(save) = save {
+        if let Err(error) = save.flush(bus) {
+            eprintln!("Failed to write {}: {error}", save.path().display());
+        }
+    }
 }